@@ -0,0 +1,148 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use maybe_rc::{MaybeArc, MaybeRc, ReadyGroup, Slab};
+use test::{black_box, Bencher};
+
+#[bench]
+fn bench_rc_new(b: &mut Bencher) {
+    b.iter(|| Rc::new(black_box(42usize)));
+}
+
+#[bench]
+fn bench_rc_materialize(b: &mut Bencher) {
+    b.iter(|| MaybeRc::new().materialize(black_box(42usize)));
+}
+
+// Small (pointer-sized or less) values are already written with a single register store and
+// materialize runs at `Rc::new` speed, the allocation dominates, so there is no dedicated fast path.
+#[bench]
+fn bench_rc_new_small(b: &mut Bencher) {
+    b.iter(|| Rc::new(black_box(42u8)));
+}
+
+#[bench]
+fn bench_rc_materialize_small(b: &mut Bencher) {
+    b.iter(|| MaybeRc::new().materialize(black_box(42u8)));
+}
+
+#[bench]
+fn bench_rc_materialize_with_weak(b: &mut Bencher) {
+    b.iter(|| {
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+        (maybe.materialize(black_box(42usize)), weak)
+    });
+}
+
+#[bench]
+fn bench_rc_materialize_big_copy(b: &mut Bencher) {
+    let value = [1.0f64; 1024];
+    b.iter(|| MaybeRc::new().materialize(*black_box(&value)));
+}
+
+#[bench]
+fn bench_rc_materialize_copied(b: &mut Bencher) {
+    let value = [1.0f64; 1024];
+    b.iter(|| MaybeRc::new().materialize_copied(black_box(&value)));
+}
+
+const ELEMENTS: usize = 4096;
+
+#[bench]
+fn bench_rc_array_generic(b: &mut Bencher) {
+    b.iter(|| MaybeRc::new().materialize(std::array::from_fn::<u64, ELEMENTS, _>(|index| black_box(index as u64))));
+}
+
+#[bench]
+fn bench_rc_array_from_fn(b: &mut Bencher) {
+    b.iter(|| MaybeRc::<[u64; ELEMENTS]>::new().materialize_from_fn(|index| black_box(index as u64)));
+}
+
+#[bench]
+fn bench_rc_slice_generic(b: &mut Bencher) {
+    let values = vec![1u64; ELEMENTS];
+    b.iter(|| MaybeRc::new().materialize(<[u64; ELEMENTS]>::try_from(black_box(&values[..])).unwrap()));
+}
+
+#[bench]
+fn bench_rc_slice_copied(b: &mut Bencher) {
+    let values = vec![1u64; ELEMENTS];
+    b.iter(|| MaybeRc::<[u64; ELEMENTS]>::new().materialize_from_slice(black_box(&values)));
+}
+
+#[bench]
+fn bench_arc_new(b: &mut Bencher) {
+    b.iter(|| Arc::new(black_box(42usize)));
+}
+
+#[bench]
+fn bench_arc_materialize(b: &mut Bencher) {
+    b.iter(|| MaybeArc::new().materialize(black_box(42usize)));
+}
+
+#[bench]
+fn bench_arc_materialize_with_weak(b: &mut Bencher) {
+    b.iter(|| {
+        let maybe = MaybeArc::new();
+        let weak = maybe.downgrade();
+        (maybe.materialize(black_box(42usize)), weak)
+    });
+}
+
+const NODES: usize = 1024;
+
+#[bench]
+fn bench_rc_nodes_per_node(b: &mut Bencher) {
+    b.iter(|| {
+        (0..NODES)
+            .map(|index| MaybeRc::new().materialize(black_box(index)))
+            .collect::<Vec<_>>()
+    });
+}
+
+#[bench]
+fn bench_rc_nodes_slab(b: &mut Bencher) {
+    b.iter(|| {
+        let slab = Slab::with_capacity(NODES);
+        (0..NODES)
+            .map(|index| slab.alloc().materialize(black_box(index)))
+            .collect::<Vec<_>>()
+    });
+}
+
+const READY_NODES: usize = 10_000;
+
+#[bench]
+fn bench_ready_scan_weaks(b: &mut Bencher) {
+    let mut weaks = Vec::new();
+    let rcs: Vec<_> = (0..READY_NODES)
+        .filter_map(|index| {
+            let maybe = MaybeRc::new();
+            weaks.push(maybe.downgrade());
+            index.is_multiple_of(2).then(|| maybe.materialize(index))
+        })
+        .collect();
+
+    b.iter(|| weaks.iter().filter(|weak| weak.strong_count() > 0).count());
+    drop(rcs);
+}
+
+#[bench]
+fn bench_ready_scan_group(b: &mut Bencher) {
+    let group = ReadyGroup::new();
+    let rcs: Vec<_> = (0..READY_NODES)
+        .filter_map(|index| {
+            let maybe = group.add();
+            index.is_multiple_of(2).then(|| maybe.materialize(index))
+        })
+        .collect();
+
+    b.iter(|| black_box(&group).count_ready());
+    drop(rcs);
+}
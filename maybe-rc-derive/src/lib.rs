@@ -0,0 +1,209 @@
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// Derives cyclic constructors for a struct with fields marked as `#[self_weak]`
+///
+/// Generates two associated functions which fill every `#[self_weak]` field
+/// with a `Weak<Self>` pointing to the newly created `Rc<Self>`:
+/// - `new(fields...) -> Rc<Self>` taking all other fields in declaration order
+/// - `try_new(f) -> Result<Rc<Self>, E>` where `f: FnOnce(&Weak<Self>) -> Result<(fields...), E>`
+///   builds all other fields and might fail, implemented with `maybe_rc::try_new_cyclic_rc`
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc_derive::CyclicNode;
+///
+/// #[derive(CyclicNode)]
+/// struct Node {
+///     #[self_weak]
+///     me: Weak<Node>,
+///     value: u32,
+/// }
+///
+/// let node = Node::new(42);
+/// assert!(Rc::ptr_eq(&node.me.upgrade().unwrap(), &node));
+/// ```
+#[proc_macro_derive(CyclicNode, attributes(self_weak))]
+pub fn derive_cyclic_node(input: TokenStream) -> TokenStream {
+    match parse_struct(input) {
+        Ok(input) => expand(&input),
+        Err(message) => format!("::std::compile_error!({:?});", message).parse().unwrap(),
+    }
+}
+
+struct Field {
+    name: String,
+    ty: String,
+    self_weak: bool,
+}
+
+struct Struct {
+    vis: String,
+    name: String,
+    fields: Vec<Field>,
+}
+
+fn expand(input: &Struct) -> TokenStream {
+    let values: Vec<&Field> = input.fields.iter().filter(|field| !field.self_weak).collect();
+
+    let args = values.iter()
+        .map(|field| format!("{}: {}", field.name, field.ty))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let names = values.iter()
+        .map(|field| format!("{},", field.name))
+        .collect::<String>();
+    let types = values.iter()
+        .map(|field| format!("{},", field.ty))
+        .collect::<String>();
+    let init = input.fields.iter()
+        .map(|field| match field.self_weak {
+            true => format!("{}: ::std::clone::Clone::clone(__maybe_rc_weak),", field.name),
+            false => format!("{},", field.name),
+        })
+        .collect::<String>();
+
+    // generated names are reserved so they can't clash with (or shadow) field names and types
+    format!(
+        r#"
+        impl {name} {{
+            {vis} fn new({args}) -> ::std::rc::Rc<Self> {{
+                ::std::rc::Rc::new_cyclic(|__maybe_rc_weak| Self {{ {init} }})
+            }}
+
+            {vis} fn try_new<__MaybeRcE, __MaybeRcF>(__maybe_rc_f: __MaybeRcF) -> ::std::result::Result<::std::rc::Rc<Self>, __MaybeRcE>
+                where
+                    __MaybeRcF: ::std::ops::FnOnce(&::std::rc::Weak<Self>) -> ::std::result::Result<({types}), __MaybeRcE>,
+            {{
+                ::maybe_rc::try_new_cyclic_rc(|__maybe_rc_weak| {{
+                    let ({names}) = __maybe_rc_f(__maybe_rc_weak)?;
+                    ::std::result::Result::Ok(Self {{ {init} }})
+                }})
+            }}
+        }}
+        "#,
+        name = input.name,
+        vis = input.vis,
+        args = args,
+        init = init,
+        types = types,
+        names = names,
+    ).parse().unwrap()
+}
+
+fn parse_struct(input: TokenStream) -> Result<Struct, String> {
+    let mut tokens = input.into_iter().peekable();
+
+    skip_attributes(&mut tokens);
+    let vis = parse_visibility(&mut tokens);
+
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => {}
+        _ => return Err("CyclicNode can only be derived for structs".to_string()),
+    }
+
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected struct name".to_string()),
+    };
+
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("CyclicNode can't be derived for generic structs".to_string());
+        }
+        _ => return Err("CyclicNode can only be derived for structs with named fields".to_string()),
+    };
+
+    let fields = parse_fields(body)?;
+    if !fields.iter().any(|field| field.self_weak) {
+        return Err("CyclicNode requires at least one `#[self_weak]` field".to_string());
+    }
+
+    Ok(Struct { vis, name, fields })
+}
+
+fn parse_fields(body: TokenStream) -> Result<Vec<Field>, String> {
+    let mut tokens = body.into_iter().peekable();
+    let mut fields = Vec::new();
+
+    while tokens.peek().is_some() {
+        let self_weak = skip_attributes(&mut tokens);
+        parse_visibility(&mut tokens);
+
+        let name = match tokens.next() {
+            Some(TokenTree::Ident(ident)) => ident.to_string(),
+            _ => return Err("expected field name".to_string()),
+        };
+
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {}
+            _ => return Err(format!("expected `:` after field `{}`", name)),
+        }
+
+        // type ends at the first comma outside of angle brackets
+        let mut ty = TokenStream::new();
+        let mut depth = 0usize;
+        let mut arrow = false;
+        for token in tokens.by_ref() {
+            if let TokenTree::Punct(punct) = &token {
+                match punct.as_char() {
+                    ',' if depth == 0 => break,
+                    '<' => depth += 1,
+                    '>' if !arrow => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                arrow = punct.as_char() == '-';
+            } else {
+                arrow = false;
+            }
+            ty.extend(Some(token));
+        }
+
+        fields.push(Field { name, ty: ty.to_string(), self_weak });
+    }
+
+    Ok(fields)
+}
+
+/// Skips outer attributes returning `true` if `#[self_weak]` was among them
+fn skip_attributes<I>(tokens: &mut std::iter::Peekable<I>) -> bool
+    where
+        I: Iterator<Item=TokenTree>,
+{
+    let mut self_weak = false;
+
+    while let Some(TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() != '#' {
+            break;
+        }
+        tokens.next();
+
+        if let Some(TokenTree::Group(group)) = tokens.next() {
+            let attribute = group.stream().to_string();
+            self_weak |= attribute == "self_weak";
+        }
+    }
+
+    self_weak
+}
+
+fn parse_visibility<I>(tokens: &mut std::iter::Peekable<I>) -> String
+    where
+        I: Iterator<Item=TokenTree>,
+{
+    match tokens.peek() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {}
+        _ => return String::new(),
+    }
+
+    let mut vis = tokens.next().unwrap().to_string();
+    if let Some(TokenTree::Group(group)) = tokens.peek() {
+        if group.delimiter() == Delimiter::Parenthesis {
+            vis += &tokens.next().unwrap().to_string();
+        }
+    }
+
+    vis
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use maybe_rc_derive::CyclicNode;
+
+#[derive(CyclicNode)]
+struct Node {
+    #[self_weak]
+    me: Weak<Node>,
+    value: u32,
+}
+
+#[derive(CyclicNode)]
+pub(crate) struct Parent {
+    name: String,
+    #[self_weak]
+    me: Weak<Parent>,
+    children: Vec<Child>,
+    tags: HashMap<String, u32>,
+    callback: Box<dyn Fn(u32) -> u32>,
+}
+
+struct E;
+
+struct F;
+
+/// Fields named like the internals of the generated constructors
+#[derive(CyclicNode)]
+struct Clashing {
+    #[self_weak]
+    me: Weak<Clashing>,
+    weak: u32,
+    f: Option<F>,
+    e: Option<E>,
+}
+
+struct Child {
+    parent: Weak<Parent>,
+}
+
+#[test]
+fn test_new() {
+    let node = Node::new(42);
+
+    assert_eq!(node.value, 42, "value is not what was provided");
+    assert!(Rc::ptr_eq(&node.me.upgrade().unwrap(), &node), "self weak must point to the node");
+}
+
+#[test]
+fn test_try_new_ok() {
+    let parent = Parent::try_new(|weak| {
+        let children = vec![Child { parent: weak.clone() }, Child { parent: weak.clone() }];
+        let callback: Box<dyn Fn(u32) -> u32> = Box::new(|x| x + 1);
+        Ok::<_, ()>(("parent".to_string(), children, HashMap::new(), callback))
+    }).unwrap();
+
+    assert_eq!(parent.name, "parent", "value is not what was provided");
+    assert!(parent.tags.is_empty(), "value is not what was provided");
+    assert_eq!((parent.callback)(41), 42, "value is not what was provided");
+    assert!(Rc::ptr_eq(&parent.me.upgrade().unwrap(), &parent), "self weak must point to the node");
+    assert!(parent.children.iter().all(|child| child.parent.as_ptr() == Rc::as_ptr(&parent)), "children must point to the node");
+}
+
+#[test]
+fn test_try_new_err() {
+    let parent = Parent::try_new(|_| Err::<(String, Vec<Child>, HashMap<String, u32>, Box<dyn Fn(u32) -> u32>), _>(42));
+
+    assert_eq!(parent.err(), Some(42), "incorrect error value");
+}
+
+#[test]
+fn test_clashing_field_names() {
+    let node = Clashing::new(42, Some(F), None);
+    assert_eq!(node.weak, 42, "value is not what was provided");
+    assert!(node.f.is_some() && node.e.is_none(), "value is not what was provided");
+    assert!(Rc::ptr_eq(&node.me.upgrade().unwrap(), &node), "self weak must point to the node");
+
+    let node = Clashing::try_new(|_| Ok::<_, ()>((7, None, Some(E)))).unwrap();
+    assert_eq!(node.weak, 7, "value is not what was provided");
+    assert!(Rc::ptr_eq(&node.me.upgrade().unwrap(), &node), "self weak must point to the node");
+}
@@ -0,0 +1,161 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// Node of a graph built with `AdjacencyBuilder`
+pub struct AdjacencyNode<T> {
+    pub value: T,
+    pub edges: Vec<Weak<RefCell<AdjacencyNode<T>>>>,
+}
+
+struct PendingNode<T> {
+    maybe: MaybeRc<RefCell<AdjacencyNode<T>>>,
+    value: T,
+    targets: Vec<usize>,
+}
+
+/// Builder of adjacency-list graphs where every node holds weak edges to other nodes
+///
+/// All nodes are allocated upfront with `MaybeRc`, so edges can point anywhere
+/// including the node itself (self-loops) and the same node multiple times (parallel edges).
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::AdjacencyBuilder;
+///
+/// let mut builder = AdjacencyBuilder::new();
+/// let a = builder.add_node("a");
+/// let b = builder.add_node("b");
+/// builder.add_edge(a, b);
+/// builder.add_edge(b, a);
+///
+/// let nodes = builder.build();
+/// let next = nodes[a].borrow().edges[0].upgrade().unwrap();
+/// assert_eq!(next.borrow().value, "b");
+/// ```
+pub struct AdjacencyBuilder<T> {
+    nodes: Vec<PendingNode<T>>,
+}
+
+impl<T> AdjacencyBuilder<T> {
+    /// Constructs a new empty `AdjacencyBuilder<T>`.
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    /// Declares a new node returning its index.
+    pub fn add_node(&mut self, value: T) -> usize {
+        self.nodes.push(PendingNode { maybe: MaybeRc::new(), value, targets: Vec::new() });
+        self.nodes.len() - 1
+    }
+
+    /// Returns a mutable reference to the value of the node at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn value_mut(&mut self, index: usize) -> &mut T {
+        &mut self.nodes[index].value
+    }
+
+    /// Adds a directed edge between nodes at `from` and `to`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn add_edge(&mut self, from: usize, to: usize) {
+        assert!(to < self.nodes.len(), "edge target {} is out of bounds", to);
+        self.nodes[from].targets.push(to);
+    }
+
+    /// Returns the number of declared nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if no nodes were declared.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Materializes all nodes in the order of declaration.
+    pub fn build(self) -> Vec<Rc<RefCell<AdjacencyNode<T>>>> {
+        let weaks: Vec<_> = self.nodes.iter()
+            .map(|node| node.maybe.downgrade())
+            .collect();
+
+        self.nodes.into_iter()
+            .map(|node| {
+                let edges = node.targets.into_iter()
+                    .map(|target| weaks[target].clone())
+                    .collect();
+                node.maybe.materialize(RefCell::new(AdjacencyNode { value: node.value, edges }))
+            })
+            .collect()
+    }
+}
+
+impl<T> Default for AdjacencyBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traverse() {
+        // 0 -> 1 -> 2 -> 0, 2 -> 2, 0 => 1 (parallel)
+        let mut builder = AdjacencyBuilder::new();
+        for value in 0..3 {
+            builder.add_node(value * 10);
+        }
+        builder.add_edge(0, 1);
+        builder.add_edge(0, 1);
+        builder.add_edge(1, 2);
+        builder.add_edge(2, 0);
+        builder.add_edge(2, 2);
+        *builder.value_mut(1) += 1;
+
+        let nodes = builder.build();
+
+        let values: Vec<_> = nodes.iter().map(|node| node.borrow().value).collect();
+        assert_eq!(values, [0, 11, 20], "incorrect values");
+
+        let targets = |index: usize| -> Vec<usize> {
+            nodes[index].borrow().edges.iter()
+                .map(|edge| edge.upgrade().expect("edge must be upgradable"))
+                .map(|target| nodes.iter().position(|node| Rc::ptr_eq(node, &target)).unwrap())
+                .collect()
+        };
+        assert_eq!(targets(0), [1, 1], "parallel edges must be kept");
+        assert_eq!(targets(1), [2], "incorrect edges");
+        assert_eq!(targets(2), [0, 2], "self-loop must be kept");
+
+        // walk 0 -> 1 -> 2 -> 0 through the weak adjacency lists
+        let mut current = nodes[0].clone();
+        let mut path = vec![current.borrow().value];
+        for _ in 0..3 {
+            let next = current.borrow().edges[0].upgrade().unwrap();
+            path.push(next.borrow().value);
+            current = next;
+        }
+        assert_eq!(path, [0, 11, 20, 0], "incorrect traversal");
+
+        // nodes are mutable after materialization
+        nodes[2].borrow_mut().edges.clear();
+        assert!(targets(2).is_empty(), "edges must be mutable");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_edge_out_of_bounds() {
+        let mut builder = AdjacencyBuilder::new();
+        let a = builder.add_node(());
+        builder.add_edge(a, 1);
+    }
+}
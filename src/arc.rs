@@ -1,6 +1,11 @@
-use std::cell::UnsafeCell;
-use std::mem::MaybeUninit;
-use std::sync::{Arc, Weak};
+use std::alloc::{Allocator, Global, Layout};
+use std::mem::{self, MaybeUninit};
+use std::sync::{Arc, UniqueArc, Weak};
+
+#[cfg(feature = "debug-locations")]
+use crate::locations::Origins;
+use crate::critical;
+use crate::drop_guard::DropGuard;
 
 /// An uninitialized version of `Arc<T>`
 ///
@@ -43,61 +48,169 @@ use std::sync::{Arc, Weak};
 ///     }
 /// }
 /// ```
-pub struct MaybeArc<T> {
-    weak: Weak<UnsafeCell<MaybeUninit<T>>>,
+///
+/// The backing allocation can be placed in a custom allocator with `MaybeArc::new_in`,
+/// e.g. one that prefers memory local to a NUMA node.
+pub struct MaybeArc<T, A: Allocator = Global> {
+    unique: UniqueArc<MaybeUninit<T>, A>,
+    #[cfg(feature = "debug-locations")]
+    _origins: Origins,
 }
 
 impl<T> MaybeArc<T> {
     /// Constructs a new `MaybeArc<T>`.
     pub fn new() -> Self {
-        // allocate Arc (strong = 1, weak = 1)
-        let strong = Arc::new(UnsafeCell::new(MaybeUninit::uninit()));
-        // create Weak (strong = 1, weak = 2)
-        Self { weak: Arc::downgrade(&strong) }
-        // drop Arc (strong = 0, weak = 1)
+        Self::new_in(Global)
+    }
+}
+
+impl<T, A: Allocator + Clone> MaybeArc<T, A> {
+    /// Constructs a new `MaybeArc<T, A>` in the provided allocator.
+    ///
+    /// The allocation is made right away, so `Weak<T, A>` references and the materialized
+    /// `Arc<T, A>` all share it.
+    pub fn new_in(alloc: A) -> Self {
+        let unique = UniqueArc::new_in(MaybeUninit::uninit(), alloc);
+        #[cfg(feature = "debug-locations")]
+        let origins = Origins::new(&*unique);
+
+        Self {
+            unique,
+            #[cfg(feature = "debug-locations")]
+            _origins: origins,
+        }
     }
 
     /// Creates a new `Weak<T>` pointer to this allocation.
     ///
     /// Upgrading this `Weak<T>` reference will fail and result in a None unless
     /// it is called after `MaybeArc<T>::materialize` finishes.
-    pub fn downgrade(&self) -> Weak<T> {
-        // SAFETY: `UnsafeCell` with `MaybeUninit` are [repr(transparent)] so they
-        // can be `stripped` down as memory layout should be the same
+    ///
+    /// With the `debug-locations` feature the call site is recorded, see `locations::arc_weak_origin`.
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn downgrade(&self) -> Weak<T, A> {
+        let (ptr, alloc) = UniqueArc::downgrade(&self.unique).into_raw_with_allocator();
+        #[cfg(feature = "debug-locations")]
+        crate::locations::record(ptr, std::panic::Location::caller());
+
+        // SAFETY: `MaybeUninit` is [repr(transparent)] so it can
+        // be `stripped` down as memory layout should be the same
         unsafe {
-            Weak::from_raw(self.weak.clone().into_raw().cast())
+            Weak::from_raw_in(ptr.cast(), alloc)
         }
     }
 
+    /// Returns a raw pointer to the (uninitialized) value of this allocation.
+    ///
+    /// The allocation never moves, so the pointer is the same for all `Weak<T>` references
+    /// and for `Arc::as_ptr` after materialization until the allocation is freed.
+    pub fn as_ptr(&self) -> *const T {
+        let ptr: *const MaybeUninit<T> = &*self.unique;
+        ptr.cast()
+    }
+
+    /// Returns the size in bytes of the backing allocation, including the reference counts.
+    ///
+    /// Layout of std's `ArcInner` is not public, so this is an approximation assuming
+    /// two `usize` counters placed before the value, which matches current std.
+    pub fn allocation_size(&self) -> usize {
+        let (layout, _) = Layout::new::<[usize; 2]>()
+            .extend(Layout::new::<T>())
+            .expect("allocation size overflow");
+        layout.pad_to_align().size()
+    }
+
     /// Materialize this allocation to a fully-contructed `Arc<T>`.
     ///
     /// All `Weak<T>` references can be upgraded after this method finishes.
-    pub fn materialize(self, value: T) -> Arc<T> {
-        let ptr = self.weak.into_raw();
+    ///
+    /// The value is written into the existing allocation and no extra allocation is made,
+    /// so even when no `Weak<T>` was created the result is as cheap as `Arc::new`.
+    ///
+    /// The node goes live with a single atomic store of the strong count (done by
+    /// `UniqueArc::into_arc`) after the value is written. A concurrent or reentrant
+    /// `Weak<T>::upgrade` either fails or sees the fully written value, there is
+    /// no intermediate state in between.
+    pub fn materialize(mut self, value: T) -> Arc<T, A> {
+        self.unique.write(value);
+        Self::into_arc(self.unique)
+    }
 
-        // SAFETY: we know that memory is still allocated because of the weak
-        // reference and no one can have access to it without unsafe code because
-        // weak is non-upgradable at this point
-        unsafe {
-            let maybe_uninit = (*ptr).get();
-            let maybe_uninit = &mut *maybe_uninit;
-            maybe_uninit.write(value);
-        }
+    /// Materialize this allocation as a leaf node that is not referenced by any `Weak<T>`.
+    ///
+    /// In debug builds panics if a `Weak<T>` to this allocation exists, which usually means
+    /// that a supposedly-leaf node was wired by mistake. Same as `materialize` in release builds.
+    pub fn materialize_leaf(self, value: T) -> Arc<T, A> {
+        let arc = self.materialize(value);
+        // weak count is not observable before materialization
+        debug_assert_eq!(Arc::weak_count(&arc), 0, "leaf node must not have weak references");
+        arc
+    }
 
-        // SAFETY: we hold a weak reference so content is still allocated
-        // ASSUMPTION: we can restore `Arc` from strong count of 0
-        unsafe {
-            // increment strong count to 1, so weak can be upgraded
-            Arc::increment_strong_count(ptr);
-        }
+    /// Materialize this allocation to a fully-contructed `Arc<T>` if `T` is not bigger than `LIMIT` bytes.
+    ///
+    /// The size is checked at compile time, so oversized values fail to build:
+    ///
+    /// ```compile_fail
+    /// use maybe_rc::MaybeArc;
+    ///
+    /// MaybeArc::new().materialize_bounded::<16>([0u8; 32]);
+    /// ```
+    ///
+    /// ```
+    /// use maybe_rc::MaybeArc;
+    ///
+    /// MaybeArc::new().materialize_bounded::<16>([0u8; 16]);
+    /// ```
+    pub fn materialize_bounded<const LIMIT: usize>(self, value: T) -> Arc<T, A> {
+        const { assert!(mem::size_of::<T>() <= LIMIT, "value is bigger than the configured limit") };
+        self.materialize(value)
+    }
 
-        // SAFETY: `UnsafeCell` with `MaybeUninit` are [repr(transparent)] so they
-        // can be `stripped` down as memory layout should be the same
-        unsafe {
-            // we can consume Weak and make Arc from it because
-            // at this point strong = 1 and weak = 1
-            Arc::from_raw(ptr.cast())
-        }
+    /// Materialize this allocation and get exclusive access to the value before sharing it.
+    ///
+    /// `f` is called while the allocation is still unique, so no other thread can
+    /// upgrade its `Weak<T>` references and observe the value until `f` returns.
+    ///
+    /// If `f` panics the value is dropped and `Weak<T>` references will never be upgradable.
+    ///
+    /// Materialization can't be reentered as it consumes `self`, code running inside `f`
+    /// (or while constructing the value) that upgrades `Weak<T>` references gets `None`.
+    pub fn materialize_then<F>(mut self, value: T, f: F) -> Arc<T, A>
+        where
+            F: FnOnce(&mut T),
+    {
+        self.unique.write(value);
+
+        // SAFETY: value was written just above
+        let mut guard = unsafe { DropGuard::new(&mut self.unique) };
+        f(guard.value());
+        guard.disarm();
+
+        Self::into_arc(self.unique)
+    }
+
+    /// Returns a pointer to the uninitialized value for wrappers initializing it in place.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut T {
+        self.unique.as_mut_ptr()
+    }
+
+    /// SAFETY: value must be fully initialized through `as_mut_ptr`
+    pub(crate) unsafe fn assume_init(self) -> Arc<T, A> {
+        Self::into_arc(self.unique)
+    }
+
+    fn into_arc(unique: UniqueArc<MaybeUninit<T>, A>) -> Arc<T, A> {
+        // SAFETY: value was written by the caller
+        critical::section(|| unsafe {
+            UniqueArc::into_arc(unique).assume_init()
+        })
+    }
+}
+
+impl<T, A: Allocator + Clone> From<&MaybeArc<T, A>> for Weak<T, A> {
+    fn from(value: &MaybeArc<T, A>) -> Self {
+        value.downgrade()
     }
 }
 
@@ -164,4 +277,171 @@ mod tests {
         drop(arc);
         assert!(weak.upgrade().is_none(), "must not be upgradable");
     }
+
+    #[test]
+    fn test_materialize_then() {
+        use std::thread;
+
+        struct Node {
+            value: usize,
+            me: Weak<Node>,
+        }
+
+        let maybe = MaybeArc::<Node>::new();
+        let weak = maybe.downgrade();
+
+        let observer = {
+            let weak = weak.clone();
+            thread::spawn(move || weak.upgrade().map(|node| node.value))
+        };
+
+        let arc = maybe.materialize_then(Node { value: 1, me: Weak::new() }, |node| {
+            node.value = 42;
+            node.me = weak.clone();
+        });
+
+        let observed = observer.join().unwrap();
+        assert!(observed.is_none() || observed == Some(42), "must not observe unfinished value");
+
+        assert_eq!(arc.value, 42, "value is not what was set in the callback");
+        assert_eq!(arc.me.as_ptr(), Arc::as_ptr(&arc), "Weak and Arc point to a different objects");
+    }
+
+    #[test]
+    fn test_allocation_size() {
+        let maybe = MaybeArc::<[u8; 3]>::new();
+        assert!(maybe.allocation_size() >= mem::size_of::<[u8; 3]>(), "must include the value");
+        assert_eq!(maybe.allocation_size() % mem::align_of::<usize>(), 0, "must be padded to the header alignment");
+
+        let maybe = MaybeArc::<u128>::new();
+        assert!(maybe.allocation_size() >= mem::size_of::<u128>() + 2 * mem::size_of::<usize>(), "must include the header");
+    }
+
+    #[test]
+    fn test_upgrade_in_materialize_then() {
+        let maybe = MaybeArc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let arc = maybe.materialize_then(1, |value| {
+            assert!(weak.upgrade().is_none(), "must not be upgradable while materializing");
+            *value = 42;
+        });
+
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+        drop(arc);
+    }
+
+    #[test]
+    fn test_upgrade_during_materialize() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+
+        for round in 0..100u64 {
+            let maybe = MaybeArc::<[u64; 16]>::new();
+            let weak = maybe.downgrade();
+            let started = Arc::new(AtomicBool::new(false));
+
+            let upgrader = {
+                let started = started.clone();
+                thread::spawn(move || loop {
+                    started.store(true, Ordering::Release);
+                    if let Some(arc) = weak.upgrade() {
+                        assert!(arc.iter().all(|&e| e == round), "must only see the fully written value");
+                        break;
+                    }
+                })
+            };
+
+            while !started.load(Ordering::Acquire) {
+                thread::yield_now();
+            }
+            let arc = maybe.materialize([round; 16]);
+
+            upgrader.join().unwrap();
+            assert_eq!(Arc::strong_count(&arc), 1, "upgraded references must be released");
+        }
+    }
+
+    #[test]
+    fn test_ptr_stable() {
+        let maybe = MaybeArc::<usize>::new();
+        let ptr = maybe.as_ptr();
+
+        let weaks: Vec<_> = (0..3).map(|_| maybe.downgrade()).collect();
+        assert_eq!(maybe.as_ptr(), ptr, "allocation must not move");
+        assert!(weaks.iter().all(|weak| weak.as_ptr() == ptr), "Weak and MaybeArc point to a different objects");
+
+        let arc = maybe.materialize(42);
+        assert_eq!(Arc::as_ptr(&arc), ptr, "allocation must not move");
+        assert!(weaks.iter().all(|weak| weak.as_ptr() == ptr), "Weak and Arc point to a different objects");
+    }
+
+    #[test]
+    fn test_materialize_leaf() {
+        let arc = MaybeArc::<usize>::new().materialize_leaf(42);
+        assert_eq!(*arc, 42, "value is not what was provided");
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "leaf node must not have weak references")]
+    fn test_materialize_leaf_with_weak() {
+        let maybe = MaybeArc::<usize>::new();
+        let _weak = maybe.downgrade();
+        maybe.materialize_leaf(42);
+    }
+
+    #[test]
+    fn test_weak_from_ref() {
+        fn take_weak(weak: impl Into<Weak<usize>>) -> Weak<usize> {
+            weak.into()
+        }
+
+        let maybe = MaybeArc::new();
+        let weak = take_weak(&maybe);
+        assert_eq!(weak.as_ptr(), maybe.as_ptr(), "Weak points to a different object");
+
+        let arc = maybe.materialize(42);
+        assert!(Weak::ptr_eq(&weak, &Arc::downgrade(&arc)), "Weak points to a different object");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+    }
+
+    #[test]
+    fn test_new_in() {
+        use std::alloc::AllocError;
+        use std::ptr::NonNull;
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Recording {
+            layouts: Arc<Mutex<Vec<Layout>>>,
+        }
+
+        unsafe impl Allocator for Recording {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                self.layouts.lock().unwrap().push(layout);
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                Global.deallocate(ptr, layout)
+            }
+        }
+
+        let alloc = Recording::default();
+        let maybe = MaybeArc::<[u64; 4], _>::new_in(alloc.clone());
+        let size = maybe.allocation_size();
+        let weak = maybe.downgrade();
+
+        let layouts = alloc.layouts.lock().unwrap().clone();
+        assert_eq!(layouts.len(), 1, "allocation must be made with the provided allocator");
+        assert_eq!(layouts[0].size(), size, "incorrect allocation layout");
+
+        let arc = maybe.materialize([1, 2, 3, 4]);
+        assert_eq!(weak.upgrade().map(|e| *e), Some([1, 2, 3, 4]), "must be upgradable");
+        assert_eq!(alloc.layouts.lock().unwrap().len(), 1, "materialization must not allocate");
+
+        drop(arc);
+        assert!(weak.upgrade().is_none(), "must not be upgradable after drop");
+    }
 }
@@ -0,0 +1,117 @@
+use std::sync::{Arc, Condvar, Mutex, Weak};
+
+use crate::MaybeArc;
+
+/// A `MaybeArc<T>` which lets a coordinator wait until enough `Weak<T>` references were created
+///
+/// Useful when several threads wire parts of a node's fan-out and the node must not be
+/// materialized before all of them registered their back-edges.
+///
+/// Weak counts are not observable before materialization, so the barrier counts calls
+/// to `MaybeArcBarrier::downgrade` instead. Dropping a `Weak<T>` doesn't decrease it.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use maybe_rc::MaybeArcBarrier;
+///
+/// let barrier = MaybeArcBarrier::new();
+/// let weaks = thread::scope(|scope| {
+///     let handles: Vec<_> = (0..4).map(|_| scope.spawn(|| barrier.downgrade())).collect();
+///     barrier.wait_for_weaks(4);
+///     handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+/// });
+///
+/// let arc = barrier.materialize(42);
+/// assert!(weaks.iter().all(|weak| weak.upgrade().is_some()));
+/// ```
+pub struct MaybeArcBarrier<T> {
+    maybe: MaybeArc<T>,
+    registered: Mutex<usize>,
+    changed: Condvar,
+}
+
+impl<T> MaybeArcBarrier<T> {
+    /// Constructs a new `MaybeArcBarrier<T>`.
+    pub fn new() -> Self {
+        Self { maybe: MaybeArc::new(), registered: Mutex::new(0), changed: Condvar::new() }
+    }
+
+    /// Creates a new `Weak<T>` pointer to this allocation and registers it.
+    pub fn downgrade(&self) -> Weak<T> {
+        let weak = self.maybe.downgrade();
+        *self.registered.lock().unwrap() += 1;
+        self.changed.notify_all();
+        weak
+    }
+
+    /// Returns the number of `Weak<T>` references created so far.
+    pub fn registered(&self) -> usize {
+        *self.registered.lock().unwrap()
+    }
+
+    /// Blocks the current thread until at least `count` `Weak<T>` references were created.
+    pub fn wait_for_weaks(&self, count: usize) {
+        let registered = self.registered.lock().unwrap();
+        let _registered = self.changed.wait_while(registered, |registered| *registered < count).unwrap();
+    }
+
+    /// Materialize this allocation to a fully-contructed `Arc<T>`.
+    pub fn materialize(self, value: T) -> Arc<T> {
+        self.maybe.materialize(value)
+    }
+}
+
+impl<T> Default for MaybeArcBarrier<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_wait_for_weaks() {
+        const THREADS: usize = 8;
+
+        let barrier = MaybeArcBarrier::<usize>::new();
+        let weaks = thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|index| {
+                    let barrier = &barrier;
+                    scope.spawn(move || {
+                        thread::sleep(Duration::from_millis(index as u64));
+                        barrier.downgrade()
+                    })
+                })
+                .collect();
+
+            barrier.wait_for_weaks(THREADS);
+            assert_eq!(barrier.registered(), THREADS, "all weaks must be registered");
+
+            handles.into_iter()
+                .map(|handle| handle.join().expect("thread must not panic"))
+                .collect::<Vec<_>>()
+        });
+
+        assert!(weaks.iter().all(|weak| weak.upgrade().is_none()), "must not be upgradable");
+
+        let arc = barrier.materialize(42);
+        for weak in &weaks {
+            assert!(weak.upgrade().is_some_and(|e| Arc::ptr_eq(&e, &arc)), "must be upgradable");
+        }
+    }
+
+    #[test]
+    fn test_wait_for_zero() {
+        let barrier = MaybeArcBarrier::new();
+        barrier.wait_for_weaks(0);
+        assert_eq!(*barrier.materialize(42), 42, "value is not what was provided");
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::{Arc, RwLock};
+
+use crate::MaybeArc;
+
+/// A shared `Arc<T>` which can be replaced with a new generation prepared through `MaybeArc<T>`
+///
+/// Next generation is allocated with `prepare` first, so subscribers can hold `Weak<T>`
+/// references to it before it goes live with `commit`.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::ArcSwapMaybe;
+///
+/// let swap = ArcSwapMaybe::new(1);
+///
+/// let next = swap.prepare();
+/// let subscriber = next.downgrade();
+/// assert!(subscriber.upgrade().is_none());
+///
+/// swap.commit(next, 2);
+/// assert_eq!(*swap.load(), 2);
+/// assert_eq!(subscriber.upgrade().map(|e| *e), Some(2));
+/// ```
+pub struct ArcSwapMaybe<T> {
+    current: RwLock<Arc<T>>,
+}
+
+impl<T> ArcSwapMaybe<T> {
+    /// Constructs a new `ArcSwapMaybe<T>` with `value` as the current generation.
+    pub fn new(value: T) -> Self {
+        Self::from_arc(Arc::new(value))
+    }
+
+    /// Constructs a new `ArcSwapMaybe<T>` with `arc` as the current generation.
+    pub fn from_arc(arc: Arc<T>) -> Self {
+        Self { current: RwLock::new(arc) }
+    }
+
+    /// Returns the current generation.
+    pub fn load(&self) -> Arc<T> {
+        self.current.read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Allocates the next generation which can be wired before it is committed.
+    pub fn prepare(&self) -> MaybeArc<T> {
+        MaybeArc::new()
+    }
+
+    /// Materializes `next` with `value` and makes it the current generation.
+    ///
+    /// Returns the previous generation.
+    pub fn commit(&self, next: MaybeArc<T>, value: T) -> Arc<T> {
+        let next = next.materialize(value);
+        let mut current = self.current.write()
+            .unwrap_or_else(|e| e.into_inner());
+        std::mem::replace(&mut *current, next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn test_commit() {
+        let swap = ArcSwapMaybe::new(0usize);
+
+        let next = swap.prepare();
+        let weak = next.downgrade();
+        assert!(weak.upgrade().is_none(), "must not be upgradable before commit");
+        assert_eq!(*swap.load(), 0, "must keep current generation before commit");
+
+        let previous = swap.commit(next, 1);
+        assert_eq!(*previous, 0, "must return previous generation");
+        assert_eq!(*swap.load(), 1, "must switch to the next generation");
+        assert!(Arc::ptr_eq(&weak.upgrade().unwrap(), &swap.load()), "Weak must point to the committed generation");
+    }
+
+    #[test]
+    fn test_concurrent_generations() {
+        const GENERATIONS: usize = 1000;
+
+        let swap = Arc::new(ArcSwapMaybe::new(0usize));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let swap = swap.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while last < GENERATIONS {
+                        let current = *swap.load();
+                        assert!(current >= last, "generations must not go back");
+                        last = current;
+                    }
+                })
+            })
+            .collect();
+
+        for generation in 1..=GENERATIONS {
+            let next = swap.prepare();
+            let weak = next.downgrade();
+            swap.commit(next, generation);
+            assert_eq!(weak.upgrade().map(|e| *e), Some(generation), "Weak must go live on commit");
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// A node of a graph that can be deep cloned with `clone_graph`
+pub trait CloneNode: Sized {
+    /// Clones this node, mapping all of its edges through the `cloner`.
+    fn clone_node(&self, cloner: &mut GraphCloner<Self>) -> Self;
+}
+
+enum Slot<T> {
+    /// Only known through a weak edge so far
+    Pending(MaybeRc<T>, Rc<T>),
+    /// Currently being cloned somewhere up the stack
+    Building(Weak<T>),
+    /// Already cloned
+    Done(Rc<T>),
+}
+
+/// Maps edges of the original graph to the edges of its clone
+///
+/// Nodes are allocated with `MaybeRc` as soon as they are discovered, so weak edges
+/// can be wired to nodes that are not cloned yet, including the node being cloned itself.
+pub struct GraphCloner<T> {
+    slots: HashMap<*const T, Slot<T>>,
+    pending: Vec<*const T>,
+}
+
+impl<T: CloneNode> GraphCloner<T> {
+    /// Maps a strong edge, cloning the target node if it was not cloned yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target is being cloned already, which means the original graph
+    /// has a cycle made of strong edges only.
+    pub fn strong(&mut self, rc: &Rc<T>) -> Rc<T> {
+        let ptr = Rc::as_ptr(rc);
+
+        let maybe = match self.slots.remove(&ptr) {
+            Some(Slot::Done(clone)) => {
+                self.slots.insert(ptr, Slot::Done(clone.clone()));
+                return clone;
+            }
+            Some(Slot::Building(_)) => panic!("graph has a cycle of strong edges"),
+            Some(Slot::Pending(maybe, _)) => maybe,
+            None => MaybeRc::new(),
+        };
+
+        self.slots.insert(ptr, Slot::Building(maybe.downgrade()));
+        let value = rc.clone_node(self);
+        let clone = maybe.materialize(value);
+        self.slots.insert(ptr, Slot::Done(clone.clone()));
+
+        clone
+    }
+
+    /// Maps a weak edge, the target is allocated now and cloned later if needed.
+    ///
+    /// Weak edges to already dropped nodes are mapped to an empty `Weak<T>`.
+    pub fn weak(&mut self, weak: &Weak<T>) -> Weak<T> {
+        let ptr = weak.as_ptr();
+
+        match self.slots.get(&ptr) {
+            Some(Slot::Pending(maybe, _)) => return maybe.downgrade(),
+            Some(Slot::Building(clone)) => return clone.clone(),
+            Some(Slot::Done(clone)) => return Rc::downgrade(clone),
+            None => {}
+        }
+
+        let rc = match weak.upgrade() {
+            Some(rc) => rc,
+            None => return Weak::new(),
+        };
+
+        let maybe = MaybeRc::new();
+        let clone = maybe.downgrade();
+        self.slots.insert(ptr, Slot::Pending(maybe, rc));
+        self.pending.push(ptr);
+
+        clone
+    }
+}
+
+/// Deep clones a graph of `Rc` nodes preserving its sharing and cycles
+///
+/// Every node reachable from `roots` is cloned exactly once. Strong edges must not form
+/// cycles (such graphs would leak anyway), cycles through weak edges are preserved.
+///
+/// Nodes reachable only through weak edges are cloned as well, but just like in the original
+/// graph they are dropped right away unless something holds a strong reference to them.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::{clone_graph, CloneNode, GraphCloner};
+///
+/// struct Node {
+///     name: String,
+///     parent: Weak<Node>,
+///     children: Vec<Rc<Node>>,
+/// }
+///
+/// impl CloneNode for Node {
+///     fn clone_node(&self, cloner: &mut GraphCloner<Self>) -> Self {
+///         Node {
+///             name: self.name.clone(),
+///             parent: cloner.weak(&self.parent),
+///             children: self.children.iter().map(|child| cloner.strong(child)).collect(),
+///         }
+///     }
+/// }
+///
+/// let root = Rc::new_cyclic(|root| Node {
+///     name: "root".to_string(),
+///     parent: Weak::new(),
+///     children: vec![Rc::new(Node {
+///         name: "child".to_string(),
+///         parent: root.clone(),
+///         children: vec![],
+///     })],
+/// });
+///
+/// let clone = clone_graph(std::slice::from_ref(&root)).remove(0);
+/// assert!(!Rc::ptr_eq(&root, &clone));
+/// assert!(Rc::ptr_eq(&clone.children[0].parent.upgrade().unwrap(), &clone));
+/// ```
+pub fn clone_graph<T: CloneNode>(roots: &[Rc<T>]) -> Vec<Rc<T>> {
+    let mut cloner = GraphCloner {
+        slots: HashMap::new(),
+        pending: Vec::new(),
+    };
+
+    let clones = roots.iter()
+        .map(|root| cloner.strong(root))
+        .collect();
+
+    // nodes discovered only through weak edges which are not cloned yet
+    while let Some(ptr) = cloner.pending.pop() {
+        if let Some(Slot::Pending(_, rc)) = cloner.slots.get(&ptr) {
+            let rc = rc.clone();
+            cloner.strong(&rc);
+        }
+    }
+
+    clones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+
+    struct Node {
+        id: usize,
+        next: Option<Rc<Node>>,
+        back: RefCell<Weak<Node>>,
+    }
+
+    impl CloneNode for Node {
+        fn clone_node(&self, cloner: &mut GraphCloner<Self>) -> Self {
+            Node {
+                id: self.id,
+                next: self.next.as_ref().map(|next| cloner.strong(next)),
+                back: RefCell::new(cloner.weak(&self.back.borrow())),
+            }
+        }
+    }
+
+    fn node(id: usize, next: Option<Rc<Node>>) -> Rc<Node> {
+        Rc::new(Node { id, next, back: RefCell::new(Weak::new()) })
+    }
+
+    #[test]
+    fn test_cycle() {
+        // a -> b -> c - - > a
+        let c = node(2, None);
+        let b = node(1, Some(c.clone()));
+        let a = node(0, Some(b.clone()));
+        *c.back.borrow_mut() = Rc::downgrade(&a);
+
+        let clone_a = clone_graph(std::slice::from_ref(&a)).remove(0);
+        let clone_b = clone_a.next.clone().unwrap();
+        let clone_c = clone_b.next.clone().unwrap();
+
+        assert_eq!((clone_a.id, clone_b.id, clone_c.id), (0, 1, 2), "incorrect values");
+        assert!(clone_c.next.is_none(), "incorrect topology");
+
+        let back = clone_c.back.borrow().upgrade().expect("back edge must be wired");
+        assert!(Rc::ptr_eq(&back, &clone_a), "back edge must point to the clone");
+
+        assert!(!Rc::ptr_eq(&a, &clone_a), "clone must be a different allocation");
+        assert!(!Rc::ptr_eq(&b, &clone_b), "clone must be a different allocation");
+        assert!(!Rc::ptr_eq(&c, &clone_c), "clone must be a different allocation");
+    }
+
+    #[test]
+    fn test_shared() {
+        let shared = node(2, None);
+        let a = node(0, Some(shared.clone()));
+        let b = node(1, Some(shared.clone()));
+        *a.back.borrow_mut() = Rc::downgrade(&shared);
+
+        let clones = clone_graph(&[a, b]);
+        let clone_shared = clones[0].next.clone().unwrap();
+
+        assert!(Rc::ptr_eq(&clone_shared, clones[1].next.as_ref().unwrap()), "sharing must be preserved");
+        assert!(Rc::ptr_eq(&clones[0].back.borrow().upgrade().unwrap(), &clone_shared), "weak must point to the shared clone");
+        assert!(!Rc::ptr_eq(&clone_shared, &shared), "clone must be a different allocation");
+    }
+
+    #[test]
+    fn test_self_weak() {
+        let a = node(0, None);
+        *a.back.borrow_mut() = Rc::downgrade(&a);
+
+        let clone = clone_graph(&[a]).remove(0);
+        assert!(Rc::ptr_eq(&clone.back.borrow().upgrade().unwrap(), &clone), "self weak must point to the clone");
+    }
+
+    #[test]
+    #[should_panic(expected = "cycle of strong edges")]
+    fn test_strong_cycle() {
+        struct Strong(RefCell<Option<Rc<Strong>>>);
+
+        impl CloneNode for Strong {
+            fn clone_node(&self, cloner: &mut GraphCloner<Self>) -> Self {
+                Strong(RefCell::new(self.0.borrow().as_ref().map(|next| cloner.strong(next))))
+            }
+        }
+
+        let a = Rc::new(Strong(RefCell::new(None)));
+        *a.0.borrow_mut() = Some(a.clone());
+        clone_graph(&[a]);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Weak;
+
+/// A queue of events keyed by node identity where later events replace earlier ones
+///
+/// Nodes are identified by the address `Weak<T>` points to, so events can be queued for nodes
+/// that are not materialized yet (e.g. right after `MaybeRc::new`). Queued `Weak<T>` references
+/// keep the allocation around, so its address can't be reused by another node until drained.
+/// All empty `Weak::new()` references share a single key.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use maybe_rc::Coalescer;
+///
+/// let node = Rc::new(());
+/// let mut coalescer = Coalescer::new();
+/// coalescer.enqueue(Rc::downgrade(&node), "first");
+/// coalescer.enqueue(Rc::downgrade(&node), "second");
+///
+/// let events: Vec<_> = coalescer.drain().into_iter().map(|(_, event)| event).collect();
+/// assert_eq!(events, ["second"]);
+/// ```
+pub struct Coalescer<T, E> {
+    events: Vec<(Weak<T>, E)>,
+    indices: HashMap<*const T, usize>,
+}
+
+impl<T, E> Coalescer<T, E> {
+    /// Constructs a new empty `Coalescer<T, E>`.
+    pub fn new() -> Self {
+        Self { events: Vec::new(), indices: HashMap::new() }
+    }
+
+    /// Queues `event` for the node `weak` points to, replacing an event queued for it before.
+    ///
+    /// Returns the replaced event.
+    pub fn enqueue(&mut self, weak: Weak<T>, event: E) -> Option<E> {
+        match self.indices.get(&weak.as_ptr()) {
+            Some(&index) => Some(mem::replace(&mut self.events[index].1, event)),
+            None => {
+                self.indices.insert(weak.as_ptr(), self.events.len());
+                self.events.push((weak, event));
+                None
+            }
+        }
+    }
+
+    /// Takes all queued events, one per node, in the order nodes were first queued.
+    pub fn drain(&mut self) -> Vec<(Weak<T>, E)> {
+        self.indices.clear();
+        mem::take(&mut self.events)
+    }
+
+    /// Returns the number of nodes with queued events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns `true` if there are no queued events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<T, E> Default for Coalescer<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    #[test]
+    fn test_coalesce() {
+        let first = Rc::new(1);
+        let second = Rc::new(2);
+
+        let mut coalescer = Coalescer::new();
+        assert_eq!(coalescer.enqueue(Rc::downgrade(&first), "a"), None, "nothing to replace");
+        assert_eq!(coalescer.enqueue(Rc::downgrade(&second), "b"), None, "nothing to replace");
+        assert_eq!(coalescer.enqueue(Rc::downgrade(&first), "c"), Some("a"), "previous event must be replaced");
+        coalescer.enqueue(Rc::downgrade(&second), "d");
+        coalescer.enqueue(Rc::downgrade(&first), "e");
+        assert_eq!(coalescer.len(), 2, "events must be coalesced per node");
+
+        let events: Vec<_> = coalescer.drain().into_iter()
+            .map(|(weak, event)| (weak.upgrade().map(|e| *e), event))
+            .collect();
+        assert_eq!(events, [(Some(1), "e"), (Some(2), "d")], "last event per node must win in first-queued order");
+        assert!(coalescer.is_empty(), "must be empty after drain");
+
+        coalescer.enqueue(Rc::downgrade(&first), "f");
+        assert_eq!(coalescer.drain().len(), 1, "must accept events after drain");
+    }
+}
@@ -0,0 +1,70 @@
+/// Runs `f` as a section that must never unwind
+///
+/// Wraps the unsafe conversion of the backing allocation into a shared `Rc<T>`/`Arc<T>`.
+/// Nothing in there is expected to panic, so a panic means one of std layout invariants the
+/// conversion relies on is broken. With the `panic-abort-critical` feature such panic aborts
+/// the process instead of unwinding through a half-converted allocation. Without the feature
+/// `f` is called as is.
+#[inline(always)]
+pub(crate) fn section<R, F>(f: F) -> R
+    where
+        F: FnOnce() -> R,
+{
+    #[cfg(feature = "panic-abort-critical")]
+    let guard = AbortOnUnwind;
+
+    let result = f();
+
+    #[cfg(feature = "panic-abort-critical")]
+    std::mem::forget(guard);
+
+    result
+}
+
+/// Aborts the process when dropped, only reachable while unwinding out of `section`
+#[cfg(feature = "panic-abort-critical")]
+struct AbortOnUnwind;
+
+#[cfg(feature = "panic-abort-critical")]
+impl Drop for AbortOnUnwind {
+    fn drop(&mut self) {
+        std::process::abort();
+    }
+}
+
+#[cfg(all(test, feature = "panic-abort-critical"))]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::process::Command;
+
+    const CHILD: &str = "MAYBE_RC_CRITICAL_CHILD";
+
+    #[test]
+    fn test_section_result() {
+        assert_eq!(section(|| 42), 42, "result must be returned");
+    }
+
+    #[test]
+    fn test_panic_aborts() {
+        if env::var_os(CHILD).is_some() {
+            section(|| -> () { panic!("broken invariant") });
+            return;
+        }
+
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["critical::tests::test_panic_aborts", "--exact"])
+            .env(CHILD, "1")
+            .status()
+            .expect("must spawn the test binary");
+
+        assert!(!status.success(), "panic must not be caught by the test harness");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(6), "process must be aborted with SIGABRT");
+        }
+    }
+}
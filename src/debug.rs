@@ -0,0 +1,244 @@
+//! Debugging helpers enabled by the `debug-registry` feature
+//!
+//! The registry only keeps addresses of nodes, it never holds references to them, so enabling it
+//! doesn't change reference counts or lifetimes of any node. `Rc<T>` nodes never leave the thread
+//! they were created on, so every thread has its own registry.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct Entry {
+    // `None` for pending nodes
+    deadline: Option<Instant>,
+}
+
+thread_local! {
+    static NODES: RefCell<HashMap<*const (), Entry>> = RefCell::new(HashMap::new());
+}
+
+fn register(ptr: *const (), entry: Entry) {
+    let _ = NODES.try_with(|nodes| nodes.borrow_mut().insert(ptr, entry));
+}
+
+fn unregister(ptr: *const ()) {
+    let _ = NODES.try_with(|nodes| {
+        let mut nodes = nodes.borrow_mut();
+        nodes.remove(&ptr);
+        // the table is released once empty, so the registry holds no memory without nodes
+        if nodes.is_empty() {
+            nodes.shrink_to_fit();
+        }
+    });
+}
+
+/// Registers a node on construction and tracks it until it is materialized or abandoned
+pub(crate) struct Tracker {
+    ptr: *const (),
+}
+
+impl Tracker {
+    pub(crate) fn new<T>(ptr: *const T) -> Self {
+        let ptr = ptr.cast::<()>();
+        register(ptr, Entry::default());
+        Self { ptr }
+    }
+}
+
+impl Drop for Tracker {
+    // node was materialized or dropped without being materialized
+    fn drop(&mut self) {
+        unregister(self.ptr);
+    }
+}
+
+/// A materialized `Rc<T>` which stays registered until this handle is dropped
+///
+/// Created by `MaybeRc::materialize_tracked`. The registry doesn't observe reference counts,
+/// so the node is reported by `live_nodes` as long as this handle is alive and by `leaked_nodes`
+/// once it outlives the expected lifetime, e.g. when it is forgotten or kept in a strong cycle.
+pub struct TrackedRc<T> {
+    rc: Rc<T>,
+}
+
+impl<T> TrackedRc<T> {
+    pub(crate) fn new(rc: Rc<T>, lifetime: Duration) -> Self {
+        let deadline = Instant::now().checked_add(lifetime);
+        register(Rc::as_ptr(&rc).cast(), Entry { deadline });
+        Self { rc }
+    }
+
+    /// Returns the tracked `Rc<T>`.
+    pub fn as_rc(this: &Self) -> &Rc<T> {
+        &this.rc
+    }
+
+    /// Stops tracking the node and returns its `Rc<T>`.
+    pub fn untrack(this: Self) -> Rc<T> {
+        Self::as_rc(&this).clone()
+    }
+}
+
+impl<T> Deref for TrackedRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.rc
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TrackedRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.rc, f)
+    }
+}
+
+impl<T> Drop for TrackedRc<T> {
+    fn drop(&mut self) {
+        unregister(Rc::as_ptr(&self.rc).cast());
+    }
+}
+
+/// Returns pointers to all live nodes registered on the current thread
+///
+/// A node is live while its `MaybeRc` is not dropped or, after materialization with
+/// `MaybeRc::materialize_tracked`, while its `TrackedRc` is not dropped. Nodes materialized
+/// in any other way are no longer tracked. Pointers are the same as returned by
+/// `MaybeRc::as_ptr` and `Rc::as_ptr`, in no particular order.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use maybe_rc::{debug, MaybeRc};
+///
+/// let maybe = MaybeRc::<usize>::new();
+/// let ptr = maybe.as_ptr().cast();
+/// assert!(debug::live_nodes().contains(&ptr));
+///
+/// let tracked = maybe.materialize_tracked(42, Duration::from_secs(1));
+/// assert!(debug::live_nodes().contains(&ptr));
+///
+/// drop(tracked);
+/// assert!(!debug::live_nodes().contains(&ptr));
+/// ```
+pub fn live_nodes() -> Vec<*const ()> {
+    NODES.with(|nodes| nodes.borrow().keys().copied().collect())
+}
+
+/// Returns pointers to nodes materialized by `MaybeRc::materialize_tracked` on the current thread
+/// which are still alive after their expected lifetime
+///
+/// Helps to catch graphs that were forgotten (e.g. with `mem::forget`) or kept alive by a cycle.
+///
+/// # Examples
+///
+/// ```
+/// use std::mem;
+/// use std::rc::Rc;
+/// use std::time::Duration;
+/// use maybe_rc::{debug, MaybeRc};
+///
+/// let tracked = MaybeRc::new().materialize_tracked(42, Duration::ZERO);
+/// let ptr = Rc::as_ptr(debug::TrackedRc::as_rc(&tracked)).cast();
+/// mem::forget(tracked);
+///
+/// assert!(debug::leaked_nodes().contains(&ptr));
+/// ```
+pub fn leaked_nodes() -> Vec<*const ()> {
+    let now = Instant::now();
+
+    NODES.with(|nodes| {
+        nodes.borrow().iter()
+            .filter(|(_, entry)| entry.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(&ptr, _)| ptr)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic::{self, AssertUnwindSafe};
+
+    use crate::MaybeRc;
+
+    #[test]
+    fn test_live_nodes() {
+        let pending = MaybeRc::<usize>::new();
+        let tracked = MaybeRc::<usize>::new();
+        let materialized = MaybeRc::<usize>::new();
+        let abandoned = MaybeRc::<usize>::new();
+
+        let pending_ptr = pending.as_ptr().cast();
+        let tracked_ptr = tracked.as_ptr().cast();
+        let materialized_ptr = materialized.as_ptr().cast();
+        let abandoned_ptr = abandoned.as_ptr().cast();
+
+        let tracked = tracked.materialize_tracked(1, Duration::from_secs(3600));
+        let rc = materialized.materialize(2);
+        drop(abandoned);
+
+        let live = live_nodes();
+        assert!(live.contains(&pending_ptr), "pending node must be live");
+        assert!(live.contains(&tracked_ptr), "tracked node must be live");
+        assert!(!live.contains(&materialized_ptr), "untracked node must not be registered");
+        assert!(!live.contains(&abandoned_ptr), "abandoned node must not be live");
+        assert_eq!(Rc::weak_count(&rc), 0, "registry must not hold weaks");
+
+        drop(tracked);
+        drop(pending);
+
+        let live = live_nodes();
+        assert!(!live.contains(&pending_ptr), "dropped node must not be live");
+        assert!(!live.contains(&tracked_ptr), "dropped node must not be live");
+    }
+
+    #[test]
+    fn test_materialize_then_panics() {
+        let maybe = MaybeRc::<usize>::new();
+        let ptr = maybe.as_ptr().cast();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            maybe.materialize_then(42, |_| panic!("callback panicked"))
+        }));
+
+        assert!(result.is_err(), "must panic");
+        assert!(!live_nodes().contains(&ptr), "abandoned node must not be live");
+    }
+
+    #[test]
+    fn test_untrack() {
+        let tracked = MaybeRc::new().materialize_tracked(42, Duration::ZERO);
+        let ptr = Rc::as_ptr(TrackedRc::as_rc(&tracked)).cast();
+
+        let rc = TrackedRc::untrack(tracked);
+        assert_eq!(*rc, 42, "value is not what was provided");
+        assert_eq!(Rc::strong_count(&rc), 1, "handle must not keep a strong reference");
+        assert!(!leaked_nodes().contains(&ptr), "untracked node must not be reported");
+    }
+
+    #[test]
+    fn test_leaked_nodes() {
+        let forgotten = MaybeRc::new().materialize_tracked(1, Duration::ZERO);
+        let dropped = MaybeRc::new().materialize_tracked(2, Duration::ZERO);
+        let young = MaybeRc::new().materialize_tracked(3, Duration::from_secs(3600));
+        let untracked = MaybeRc::new().materialize(4);
+
+        let forgotten_ptr = Rc::as_ptr(TrackedRc::as_rc(&forgotten)).cast();
+        let dropped_ptr = Rc::as_ptr(TrackedRc::as_rc(&dropped)).cast();
+        std::mem::forget(forgotten);
+        drop(dropped);
+
+        let leaked = leaked_nodes();
+        assert!(leaked.contains(&forgotten_ptr), "forgotten node must be reported");
+        assert!(!leaked.contains(&dropped_ptr), "dropped node must not be reported");
+        assert!(!leaked.contains(&Rc::as_ptr(TrackedRc::as_rc(&young)).cast()), "node within its lifetime must not be reported");
+        assert!(!leaked.contains(&Rc::as_ptr(&untracked).cast()), "untracked node must not be reported");
+    }
+}
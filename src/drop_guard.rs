@@ -0,0 +1,62 @@
+use std::mem::{self, MaybeUninit};
+
+/// Drops an initialized `MaybeUninit<T>` value in place unless it is disarmed
+///
+/// Used to avoid leaking the value when user code panics after it was written.
+pub(crate) struct DropGuard<'a, T> {
+    slot: &'a mut MaybeUninit<T>,
+}
+
+impl<'a, T> DropGuard<'a, T> {
+    /// SAFETY: `slot` must be initialized
+    pub(crate) unsafe fn new(slot: &'a mut MaybeUninit<T>) -> Self {
+        Self { slot }
+    }
+
+    pub(crate) fn value(&mut self) -> &mut T {
+        // SAFETY: guaranteed to be initialized by the caller of `new`
+        unsafe {
+            self.slot.assume_init_mut()
+        }
+    }
+
+    pub(crate) fn disarm(self) {
+        mem::forget(self);
+    }
+}
+
+impl<'a, T> Drop for DropGuard<'a, T> {
+    fn drop(&mut self) {
+        // SAFETY: guaranteed to be initialized by the caller of `new`
+        unsafe {
+            self.slot.assume_init_drop();
+        }
+    }
+}
+
+/// Drops the first `len` initialized elements starting at `ptr` unless it is disarmed
+///
+/// Used by in-place array initializers to drop already written elements when producing the next one panics.
+pub(crate) struct PrefixGuard<T> {
+    ptr: *mut T,
+    pub(crate) len: usize,
+}
+
+impl<T> PrefixGuard<T> {
+    pub(crate) fn new(ptr: *mut T) -> Self {
+        Self { ptr, len: 0 }
+    }
+
+    pub(crate) fn disarm(self) {
+        mem::forget(self);
+    }
+}
+
+impl<T> Drop for PrefixGuard<T> {
+    fn drop(&mut self) {
+        // SAFETY: first `len` elements were written by the owner of the guard
+        unsafe {
+            std::ptr::slice_from_raw_parts_mut(self.ptr, self.len).drop_in_place();
+        }
+    }
+}
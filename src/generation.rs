@@ -0,0 +1,175 @@
+use std::cell::Cell;
+use std::mem;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+struct GenNode<T> {
+    generation: Cell<u64>,
+    value: T,
+}
+
+/// A `MaybeRc<T>` whose allocation carries a generation counter
+///
+/// Plain `Weak<T>` references keep their allocation from being freed and reused, so they can't
+/// observe a different occupant by themselves. Pools that reuse a slot in place with
+/// `GenRc::recycle` do put a new value behind old references though. `GenWeak<T>` references are
+/// tagged with the generation they were created for and stop upgrading once the slot is recycled.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::{GenMaybeRc, GenRc};
+///
+/// let maybe = GenMaybeRc::new();
+/// let stale = maybe.downgrade();
+/// let rc = maybe.materialize("first");
+///
+/// let (rc, old) = GenRc::recycle(rc, "second").ok().unwrap();
+/// assert_eq!(old, "first");
+/// assert!(stale.upgrade().is_none());
+/// assert_eq!(GenRc::downgrade(&rc).upgrade().map(|e| *e), Some("second"));
+/// ```
+pub struct GenMaybeRc<T> {
+    maybe: MaybeRc<GenNode<T>>,
+}
+
+/// Shared reference created by `GenMaybeRc::materialize`
+pub struct GenRc<T> {
+    rc: Rc<GenNode<T>>,
+}
+
+/// Weak reference tagged with the generation of the slot at `downgrade` time
+pub struct GenWeak<T> {
+    weak: Weak<GenNode<T>>,
+    generation: u64,
+}
+
+impl<T> GenMaybeRc<T> {
+    /// Constructs a new `GenMaybeRc<T>` at generation `0`.
+    pub fn new() -> Self {
+        Self { maybe: MaybeRc::new() }
+    }
+
+    /// Creates a new `GenWeak<T>` pointer to the first generation of this allocation.
+    pub fn downgrade(&self) -> GenWeak<T> {
+        GenWeak { weak: self.maybe.downgrade(), generation: 0 }
+    }
+
+    /// Materialize this allocation to a fully-contructed `GenRc<T>`.
+    pub fn materialize(self, value: T) -> GenRc<T> {
+        GenRc { rc: self.maybe.materialize(GenNode { generation: Cell::new(0), value }) }
+    }
+}
+
+impl<T> Default for GenMaybeRc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> GenRc<T> {
+    /// Returns the current generation of this slot.
+    pub fn generation(this: &Self) -> u64 {
+        this.rc.generation.get()
+    }
+
+    /// Creates a new `GenWeak<T>` pointer tagged with the current generation.
+    pub fn downgrade(this: &Self) -> GenWeak<T> {
+        GenWeak { weak: Rc::downgrade(&this.rc), generation: Self::generation(this) }
+    }
+
+    /// Reuses this slot for `value` returning the previous occupant.
+    ///
+    /// The generation is incremented, so all existing `GenWeak<T>` references stop upgrading.
+    /// Fails and returns `this` back if other strong references to the slot are alive.
+    pub fn recycle(mut this: Self, value: T) -> Result<(Self, T), Self> {
+        if Rc::strong_count(&this.rc) != 1 {
+            return Err(this);
+        }
+
+        // SAFETY: this is the only strong reference and `Rc<T>` is not shared between threads,
+        // so no `Weak<T>` can be upgraded and dereferenced while the node is borrowed
+        let node = unsafe { Rc::get_mut_unchecked(&mut this.rc) };
+        node.generation.set(node.generation.get().checked_add(1).expect("generation overflow"));
+        let old = mem::replace(&mut node.value, value);
+        Ok((this, old))
+    }
+
+    /// Returns `true` if both references point to the same slot.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Rc::ptr_eq(&this.rc, &other.rc)
+    }
+}
+
+impl<T> Deref for GenRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.rc.value
+    }
+}
+
+impl<T> Clone for GenRc<T> {
+    fn clone(&self) -> Self {
+        Self { rc: self.rc.clone() }
+    }
+}
+
+impl<T> GenWeak<T> {
+    /// Attempts to upgrade to a `GenRc<T>`.
+    ///
+    /// Returns `None` if the slot was recycled since this reference was created,
+    /// the node was dropped or it was not materialized yet.
+    pub fn upgrade(&self) -> Option<GenRc<T>> {
+        let rc = self.weak.upgrade()?;
+        (rc.generation.get() == self.generation).then_some(GenRc { rc })
+    }
+
+    /// Returns the generation this reference was created for.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl<T> Clone for GenWeak<T> {
+    fn clone(&self) -> Self {
+        Self { weak: self.weak.clone(), generation: self.generation }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_weak() {
+        let maybe = GenMaybeRc::new();
+        let first = maybe.downgrade();
+        assert!(first.upgrade().is_none(), "must not be upgradable before materialization");
+
+        let rc = maybe.materialize(1);
+        assert_eq!(first.upgrade().map(|e| *e), Some(1), "current generation must be upgradable");
+
+        let (rc, old) = GenRc::recycle(rc, 2).ok().expect("unique slot must be recyclable");
+        assert_eq!((old, GenRc::generation(&rc)), (1, 1), "slot must move to the next generation");
+        assert!(first.upgrade().is_none(), "stale generation must not be upgradable");
+
+        let second = GenRc::downgrade(&rc);
+        let upgraded = second.upgrade().expect("current generation must be upgradable");
+        assert!(GenRc::ptr_eq(&upgraded, &rc), "must upgrade to the same slot");
+        assert_eq!((*upgraded, second.generation()), (2, 1), "must see the new occupant");
+
+        let rc = GenRc::recycle(rc, 3).err().expect("shared slot must not be recyclable");
+        drop(upgraded);
+
+        let (rc, _) = GenRc::recycle(rc, 3).ok().expect("unique slot must be recyclable");
+        assert!(second.upgrade().is_none() && first.upgrade().is_none(), "stale generations must not be upgradable");
+        let third = GenRc::downgrade(&rc);
+        assert_eq!(third.upgrade().map(|e| *e), Some(3), "current generation must be upgradable");
+
+        drop(rc);
+        assert!(third.upgrade().is_none(), "dropped node must not be upgradable");
+    }
+}
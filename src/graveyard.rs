@@ -0,0 +1,168 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+thread_local! {
+    static GRAVEYARD: RefCell<Vec<Box<dyn Any>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An `Rc<T>` that defers dropping of its value to the thread-local graveyard
+///
+/// Created by `MaybeRc::materialize_deferred`. When the last `DeferredRc<T>` is dropped the
+/// value is not dropped right away but pushed to the graveyard, which is processed
+/// iteratively by `drain_graveyard`. Dropping a long chain of nodes linked with
+/// `DeferredRc<T>` therefore doesn't recurse and `Drop` implementations that upgrade
+/// `Weak<T>` references run outside of the destructor that released them.
+///
+/// Deferred values stay alive until drained, so their `Weak<T>` references can still be upgraded.
+///
+/// Values still in the graveyard when the thread exits are dropped recursively.
+pub struct DeferredRc<T: 'static> {
+    rc: ManuallyDrop<Rc<T>>,
+}
+
+impl<T> MaybeRc<T> {
+    /// Materialize this allocation to a `DeferredRc<T>` whose drop is deferred to the graveyard.
+    pub fn materialize_deferred(self, value: T) -> DeferredRc<T>
+        where
+            T: 'static,
+    {
+        DeferredRc { rc: ManuallyDrop::new(self.materialize(value)) }
+    }
+}
+
+impl<T: 'static> DeferredRc<T> {
+    /// Creates a new `Weak<T>` pointer to this allocation.
+    pub fn downgrade(this: &Self) -> Weak<T> {
+        Rc::downgrade(&this.rc)
+    }
+
+    /// Returns the underlying `Rc<T>`, it is dropped normally from now on.
+    pub fn into_rc(this: Self) -> Rc<T> {
+        let mut this = ManuallyDrop::new(this);
+
+        // SAFETY: `this` is never used again and its `Drop` is suppressed
+        unsafe { ManuallyDrop::take(&mut this.rc) }
+    }
+}
+
+impl<T: 'static> Deref for DeferredRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.rc
+    }
+}
+
+impl<T: 'static> Clone for DeferredRc<T> {
+    fn clone(&self) -> Self {
+        Self { rc: self.rc.clone() }
+    }
+}
+
+impl<T: 'static> Drop for DeferredRc<T> {
+    fn drop(&mut self) {
+        // SAFETY: `rc` is never used again
+        let rc = unsafe { ManuallyDrop::take(&mut self.rc) };
+
+        // only the last strong reference drops the value
+        if Rc::strong_count(&rc) > 1 {
+            return;
+        }
+
+        let mut rc = Some(rc);
+        let _ = GRAVEYARD.try_with(|graveyard| {
+            if let Ok(mut graveyard) = graveyard.try_borrow_mut() {
+                graveyard.push(Box::new(rc.take()));
+            }
+        });
+
+        // graveyard is not available, e.g. during thread exit
+        drop(rc);
+    }
+}
+
+/// Drops all values deferred to the graveyard of the current thread
+///
+/// Values dropped here might defer more values, they are processed by the same call
+/// one at a time, so the stack depth doesn't depend on the length of the chain.
+/// Returns the number of dropped values.
+pub fn drain_graveyard() -> usize {
+    let mut count = 0;
+    loop {
+        let Some(value) = GRAVEYARD.with(|graveyard| graveyard.borrow_mut().pop()) else {
+            return count;
+        };
+
+        // value is dropped outside of the borrow as it can push more values
+        drop(value);
+        count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::thread;
+
+    thread_local! {
+        static DROPS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct Node {
+        _next: Option<DeferredRc<Node>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+
+    #[test]
+    fn test_long_chain() {
+        const LENGTH: usize = 100_000;
+
+        // small stack makes a recursive drop of the chain overflow
+        let handle = thread::Builder::new().stack_size(128 * 1024).spawn(|| {
+            let mut head = None;
+            for _ in 0..LENGTH {
+                head = Some(MaybeRc::new().materialize_deferred(Node { _next: head }));
+            }
+
+            let weak = DeferredRc::downgrade(head.as_ref().unwrap());
+            drop(head);
+            assert_eq!(DROPS.with(Cell::get), 0, "values must be deferred");
+            assert!(weak.upgrade().is_some(), "deferred node must be alive until drained");
+
+            assert_eq!(drain_graveyard(), LENGTH, "all nodes must be drained");
+            assert!(weak.upgrade().is_none(), "drained node must not be upgradable");
+            assert_eq!(DROPS.with(Cell::get), LENGTH, "all nodes must be dropped");
+            assert_eq!(drain_graveyard(), 0, "graveyard must be empty");
+        });
+
+        handle.expect("must spawn a thread").join().expect("must not overflow the stack");
+    }
+
+    #[test]
+    fn test_shared_not_deferred() {
+        let first = MaybeRc::new().materialize_deferred(42);
+        let second = first.clone();
+        let weak = DeferredRc::downgrade(&first);
+
+        drop(first);
+        assert_eq!(drain_graveyard(), 0, "shared value must not be deferred");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+
+        let rc = DeferredRc::into_rc(second);
+        drop(rc);
+        assert_eq!(drain_graveyard(), 0, "value of a plain rc must not be deferred");
+        assert!(weak.upgrade().is_none(), "must not be upgradable");
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::MaybeRc;
+
+/// Deduplicates equal nodes while a graph is being constructed
+///
+/// Uninitialized nodes can't be hashed, so interning happens when a `MaybeRc<T>` is
+/// materialized through `Interner::materialize`: the value is checked against already
+/// interned nodes first and only committed into its own allocation if no equal node exists.
+///
+/// When an equal node exists the `MaybeRc<T>` is dropped, so all `Weak<T>` references
+/// created from it (e.g. back-edges stored in its children) are never upgradable.
+/// Use `Interner::get` to check for duplicates before wiring such references.
+///
+/// Interned nodes are kept alive by the interner until it is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use maybe_rc::{Interner, MaybeRc};
+///
+/// let mut interner = Interner::new();
+/// let a = interner.materialize(MaybeRc::new(), "leaf");
+/// let b = interner.materialize(MaybeRc::new(), "leaf");
+/// assert!(Rc::ptr_eq(&a, &b));
+/// ```
+pub struct Interner<T> {
+    nodes: HashSet<Rc<T>>,
+}
+
+impl<T: Hash + Eq> Interner<T> {
+    /// Constructs a new empty `Interner<T>`.
+    pub fn new() -> Self {
+        Self { nodes: HashSet::new() }
+    }
+
+    /// Returns an already interned node equal to `value`.
+    pub fn get(&self, value: &T) -> Option<Rc<T>> {
+        self.nodes.get(value).cloned()
+    }
+
+    /// Materializes `maybe` with `value` unless an equal node was interned already.
+    ///
+    /// Returns the existing node and drops `maybe` if an equal one exists.
+    pub fn materialize(&mut self, maybe: MaybeRc<T>, value: T) -> Rc<T> {
+        if let Some(existing) = self.nodes.get(&value) {
+            return existing.clone();
+        }
+
+        let rc = maybe.materialize(value);
+        self.nodes.insert(rc.clone());
+        rc
+    }
+
+    /// Returns the number of interned nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if no nodes were interned.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl<T: Hash + Eq> Default for Interner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_values_shared() {
+        let mut interner = Interner::new();
+
+        let first = interner.materialize(MaybeRc::new(), 42);
+        let duplicate = MaybeRc::new();
+        let weak = duplicate.downgrade();
+        let second = interner.materialize(duplicate, 42);
+
+        assert!(Rc::ptr_eq(&first, &second), "equal values must share an allocation");
+        assert!(weak.upgrade().is_none(), "deduplicated node must not be upgradable");
+        assert_eq!(interner.len(), 1, "duplicate must not be interned");
+    }
+
+    #[test]
+    fn test_distinct_values() {
+        let mut interner = Interner::new();
+
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+        let first = interner.materialize(maybe, 1);
+        let second = interner.materialize(MaybeRc::new(), 2);
+
+        assert!(!Rc::ptr_eq(&first, &second), "distinct values must not share an allocation");
+        assert!(weak.upgrade().is_some_and(|rc| Rc::ptr_eq(&rc, &first)), "new node must be upgradable");
+        assert_eq!(interner.get(&2).map(|rc| *rc), Some(2), "interned node must be found");
+        assert!(interner.get(&3).is_none(), "unknown value must not be found");
+        assert_eq!(interner.len(), 2, "both values must be interned");
+    }
+}
@@ -0,0 +1,146 @@
+use std::cell::{Cell, OnceCell};
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// A cyclic `Rc<T>` which is built on first access
+///
+/// The closure receives a `Weak<T>` to the node being constructed, so the value can
+/// reference itself. The first `LazyCyclic::get` materializes the node and subsequent
+/// calls return the cached `Rc<T>`.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::LazyCyclic;
+///
+/// struct Node {
+///     me: Weak<Node>,
+/// }
+///
+/// let lazy = LazyCyclic::new(|me: &Weak<Node>| Node { me: me.clone() });
+/// let node = lazy.get();
+/// assert!(Rc::ptr_eq(&node.me.upgrade().unwrap(), &node));
+/// assert!(Rc::ptr_eq(&lazy.get(), &node));
+/// ```
+pub struct LazyCyclic<T, F = fn(&Weak<T>) -> T> {
+    cell: OnceCell<Rc<T>>,
+    init: Cell<Option<F>>,
+    initializing: Cell<bool>,
+}
+
+/// Resets the initializing flag even if the closure panics
+struct Initializing<'a>(&'a Cell<bool>);
+
+impl Drop for Initializing<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+impl<T, F> LazyCyclic<T, F>
+    where
+        F: FnOnce(&Weak<T>) -> T,
+{
+    /// Constructs a new `LazyCyclic<T>` which will be built with `init`.
+    pub const fn new(init: F) -> Self {
+        Self { cell: OnceCell::new(), init: Cell::new(Some(init)), initializing: Cell::new(false) }
+    }
+
+    /// Returns the node, building it on the first call.
+    ///
+    /// `Weak<T>` references upgraded while the node is being built return `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within its own initialization closure or
+    /// if the initialization closure panicked before.
+    pub fn get(&self) -> Rc<T> {
+        if let Some(rc) = self.cell.get() {
+            return rc.clone();
+        }
+
+        assert!(!self.initializing.get(), "reentrant initialization of LazyCyclic");
+        let init = self.init.take().expect("LazyCyclic initialization has panicked before");
+
+        let rc = {
+            self.initializing.set(true);
+            let _initializing = Initializing(&self.initializing);
+
+            let maybe = MaybeRc::new();
+            let value = init(&maybe.downgrade());
+            maybe.materialize(value)
+        };
+
+        self.cell.get_or_init(|| rc).clone()
+    }
+
+    /// Returns the node if it was built already.
+    pub fn get_if_init(&self) -> Option<Rc<T>> {
+        self.cell.get().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn test_first_access() {
+        let calls = Cell::new(0);
+        let lazy = LazyCyclic::new(|me: &Weak<usize>| {
+            calls.set(calls.get() + 1);
+            assert!(me.upgrade().is_none(), "must not be upgradable while building");
+            42
+        });
+
+        assert!(lazy.get_if_init().is_none(), "must not be built before access");
+        assert_eq!(calls.get(), 0, "must not be built before access");
+
+        let first = lazy.get();
+        let second = lazy.get();
+        assert_eq!(*first, 42, "value is not what was built");
+        assert!(Rc::ptr_eq(&first, &second), "node must be cached");
+        assert_eq!(calls.get(), 1, "must be built exactly once");
+    }
+
+    #[test]
+    fn test_self_reference() {
+        struct Node {
+            me: Weak<Node>,
+        }
+
+        let lazy = LazyCyclic::new(|me: &Weak<Node>| Node { me: me.clone() });
+        let node = lazy.get();
+        assert!(node.me.upgrade().is_some_and(|me| Rc::ptr_eq(&me, &node)), "self weak points to a different object");
+    }
+
+    type Reentrant = LazyCyclic<usize, Box<dyn FnOnce(&Weak<usize>) -> usize>>;
+
+    #[test]
+    #[should_panic(expected = "reentrant initialization")]
+    fn test_reentrant() {
+        let lazy = Rc::new_cyclic(|lazy: &Weak<Reentrant>| {
+            let lazy = lazy.clone();
+            LazyCyclic::new(Box::new(move |_: &Weak<usize>| *lazy.upgrade().unwrap().get()) as Box<_>)
+        });
+        lazy.get();
+    }
+
+    #[test]
+    fn test_poisoned() {
+        let lazy = LazyCyclic::new(|_: &Weak<usize>| panic!("init panicked"));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| lazy.get()));
+        assert!(result.is_err(), "must panic");
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| lazy.get()));
+        let payload = result.expect_err("must panic again");
+        let message = payload.downcast_ref::<String>().map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied());
+        assert_eq!(message, Some("LazyCyclic initialization has panicked before"), "must be poisoned");
+    }
+}
@@ -1,6 +1,112 @@
+#![cfg_attr(feature = "nightly", feature(unique_rc_arc, allocator_api, async_iterator, get_mut_unchecked, unsize))]
+#![cfg_attr(feature = "debug-locations", feature(btreemap_alloc))]
+
+#[cfg(feature = "derive")]
+pub use maybe_rc_derive::CyclicNode;
+pub use coalescer::*;
+pub use maybe_shared::*;
+pub use maybe_weak::*;
+#[cfg(feature = "trace")]
+pub use trace::*;
 pub use try_new_cyclic_rc::*;
+pub use weak_registry::*;
+#[cfg(feature = "nightly")]
+pub use rc::*;
+#[cfg(feature = "nightly")]
+pub use adjacency::*;
+#[cfg(feature = "nightly")]
+pub use clone_graph::*;
+#[cfg(feature = "nightly")]
+pub use arc::*;
+#[cfg(feature = "nightly")]
+pub use arc_barrier::*;
+#[cfg(feature = "nightly")]
+pub use generation::*;
+#[cfg(feature = "nightly")]
+pub use graveyard::*;
+#[cfg(feature = "nightly")]
+pub use interner::*;
+#[cfg(feature = "nightly")]
+pub use arc_swap::*;
+#[cfg(feature = "nightly")]
+pub use lazy_cyclic::*;
+#[cfg(feature = "nightly")]
+pub use notify::*;
+#[cfg(feature = "nightly")]
+pub use placeholder::*;
+#[cfg(feature = "nightly")]
+pub use ready_arc::*;
+#[cfg(feature = "nightly")]
+pub use ready_group::*;
+#[cfg(feature = "nightly")]
+pub use revocable::*;
+#[cfg(feature = "nightly")]
+pub use slab::*;
+#[cfg(feature = "static-buffer")]
+pub use static_buffer::*;
+#[cfg(feature = "nightly")]
+pub use thin::*;
+#[cfg(feature = "nightly")]
+pub use topic::*;
+#[cfg(feature = "nightly")]
+pub use topological::*;
+#[cfg(feature = "nightly")]
+pub use tree_builder::*;
 
+mod coalescer;
+mod maybe_shared;
+mod maybe_weak;
+#[cfg(feature = "trace")]
+mod trace;
 mod try_new_cyclic_rc;
-
-// mod rc; TODO discussed... might be impossible do safely implement at this time
-// mod arc; TODO discussed... might be impossible do safely implement at this time
+mod weak_registry;
+#[cfg(feature = "nightly")]
+mod rc;
+#[cfg(feature = "nightly")]
+mod adjacency;
+#[cfg(feature = "nightly")]
+mod arc;
+#[cfg(feature = "nightly")]
+mod arc_barrier;
+#[cfg(feature = "nightly")]
+mod arc_swap;
+#[cfg(feature = "nightly")]
+mod clone_graph;
+#[cfg(feature = "nightly")]
+mod critical;
+#[cfg(feature = "debug-registry")]
+pub mod debug;
+#[cfg(feature = "nightly")]
+mod drop_guard;
+#[cfg(feature = "nightly")]
+mod generation;
+#[cfg(feature = "nightly")]
+mod graveyard;
+#[cfg(feature = "nightly")]
+mod interner;
+#[cfg(feature = "nightly")]
+mod lazy_cyclic;
+#[cfg(feature = "debug-locations")]
+pub mod locations;
+#[cfg(feature = "nightly")]
+mod notify;
+#[cfg(feature = "nightly")]
+mod placeholder;
+#[cfg(feature = "nightly")]
+mod ready_arc;
+#[cfg(feature = "nightly")]
+mod ready_group;
+#[cfg(feature = "nightly")]
+mod revocable;
+#[cfg(feature = "nightly")]
+mod slab;
+#[cfg(feature = "static-buffer")]
+mod static_buffer;
+#[cfg(feature = "nightly")]
+mod thin;
+#[cfg(feature = "nightly")]
+mod topic;
+#[cfg(feature = "nightly")]
+mod topological;
+#[cfg(feature = "nightly")]
+mod tree_builder;
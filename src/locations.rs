@@ -0,0 +1,167 @@
+//! Call site tracking of `downgrade` enabled by the `debug-locations` feature
+
+use std::alloc::{Allocator, System};
+use std::collections::BTreeMap;
+use std::panic::Location;
+use std::rc;
+use std::sync::{self, Mutex};
+
+type Locations = Vec<&'static Location<'static>>;
+
+// the table allocates straight from `System`, so it doesn't show up in the global allocator
+static ORIGINS: Mutex<BTreeMap<usize, Vec<&'static Location<'static>, System>, System>> =
+    Mutex::new(BTreeMap::new_in(System));
+
+pub(crate) fn record<T>(ptr: *const T, location: &'static Location<'static>) {
+    ORIGINS.lock().unwrap()
+        .entry(ptr as *const () as usize)
+        .or_insert_with(|| Vec::new_in(System))
+        .push(location);
+}
+
+/// Keeps call sites of a pending node and forgets them once it is materialized or dropped
+pub(crate) struct Origins {
+    ptr: usize,
+}
+
+impl Origins {
+    pub(crate) fn new<T>(ptr: *const T) -> Self {
+        Self { ptr: ptr as *const () as usize }
+    }
+}
+
+impl Drop for Origins {
+    fn drop(&mut self) {
+        if let Ok(mut origins) = ORIGINS.lock() {
+            origins.remove(&self.ptr);
+        }
+    }
+}
+
+fn origins(ptr: *const ()) -> Locations {
+    ORIGINS.lock().unwrap()
+        .get(&(ptr as usize))
+        .map(|locations| locations.to_vec())
+        .unwrap_or_default()
+}
+
+/// Returns call sites of all `MaybeRc::downgrade` calls of the pending node `weak` points to
+///
+/// Locations are listed in call order. All `Weak<T>` references to the same allocation
+/// are indistinguishable, so the list covers clones and already dropped references too.
+/// Call sites are only kept until the node is materialized or its `MaybeRc` is dropped,
+/// the list is empty afterwards.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::{locations, MaybeRc};
+///
+/// let maybe = MaybeRc::<usize>::new();
+/// let first = maybe.downgrade();
+/// let second = maybe.downgrade();
+/// assert_eq!(locations::weak_origins(&first).len(), 2);
+///
+/// maybe.materialize(42);
+/// assert!(locations::weak_origins(&first).is_empty());
+/// ```
+pub fn weak_origins<T>(weak: &rc::Weak<T>) -> Locations {
+    origins(weak.as_ptr().cast())
+}
+
+/// Returns the call site of the latest `MaybeRc::downgrade` of the pending node `weak` points to
+///
+/// Same as the last of `weak_origins`.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::{locations, MaybeRc};
+///
+/// let maybe = MaybeRc::<usize>::new();
+/// let weak = maybe.downgrade();
+/// assert_eq!(locations::weak_origin(&weak).map(|location| location.file()), Some(file!()));
+/// ```
+pub fn weak_origin<T>(weak: &rc::Weak<T>) -> Option<&'static Location<'static>> {
+    weak_origins(weak).pop()
+}
+
+/// Returns call sites of all `MaybeArc::downgrade` calls of the pending node `weak` points to
+///
+/// Same as `weak_origins` for `sync::Weak<T>` references.
+pub fn arc_weak_origins<T, A: Allocator>(weak: &sync::Weak<T, A>) -> Locations {
+    origins(weak.as_ptr().cast())
+}
+
+/// Returns the call site of the latest `MaybeArc::downgrade` of the pending node `weak` points to
+///
+/// Same as `weak_origin` for `sync::Weak<T>` references.
+pub fn arc_weak_origin<T, A: Allocator>(weak: &sync::Weak<T, A>) -> Option<&'static Location<'static>> {
+    arc_weak_origins(weak).pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use crate::{MaybeArc, MaybeRc};
+
+    #[test]
+    fn test_distinct_call_sites() {
+        let first = MaybeRc::<usize>::new();
+        let second = MaybeRc::<usize>::new();
+
+        let first_weak = first.downgrade();
+        let second_weak = second.downgrade();
+
+        let first_origin = weak_origin(&first_weak).expect("location must be recorded");
+        let second_origin = weak_origin(&second_weak).expect("location must be recorded");
+        assert_eq!(first_origin.file(), file!(), "location must point to the caller");
+        assert_ne!(first_origin.line(), second_origin.line(), "call sites must be distinct");
+
+        drop(second);
+        assert!(weak_origin(&second_weak).is_none(), "locations of dropped node must be forgotten");
+
+        let rc = first.materialize(42);
+        assert!(weak_origin(&first_weak).is_none(), "locations must be forgotten on materialization");
+        assert!(weak_origin(&Rc::downgrade(&rc)).is_none(), "locations must be forgotten on materialization");
+    }
+
+    #[test]
+    fn test_same_node_call_sites() {
+        let maybe = MaybeRc::<usize>::new();
+        let line = line!() + 1;
+        let first = maybe.downgrade();
+        let second = maybe.downgrade();
+
+        let lines: Vec<_> = weak_origins(&first).iter().map(|location| location.line()).collect();
+        assert_eq!(lines, [line, line + 1], "both call sites must be recorded");
+        assert_eq!(weak_origin(&second).map(|location| location.line()), Some(line + 1), "latest call site must be reported");
+    }
+
+    #[test]
+    fn test_reused_address() {
+        let maybe = MaybeRc::<usize>::new();
+        drop(maybe.downgrade());
+        drop(maybe);
+
+        // std usually places the next node of the same size at the freed address
+        let rc = Rc::new(42usize);
+        assert!(weak_origins(&Rc::downgrade(&rc)).is_empty(), "unrelated node must not have locations");
+    }
+
+    #[test]
+    fn test_arc_call_site() {
+        let maybe = MaybeArc::<usize>::new();
+        let line = line!() + 1;
+        let weak = maybe.downgrade();
+
+        let origin = arc_weak_origin(&weak).expect("location must be recorded");
+        assert_eq!((origin.file(), origin.line()), (file!(), line), "location must point to the caller");
+
+        drop(maybe.materialize(42));
+        assert!(arc_weak_origin(&weak).is_none(), "locations must be forgotten on materialization");
+    }
+}
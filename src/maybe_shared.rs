@@ -0,0 +1,110 @@
+use std::rc::{Rc, Weak};
+
+/// Common interface of nodes that can hand out `Weak<T>` references
+///
+/// Allows generic code to wire nodes the same way regardless of whether
+/// they are still under construction (`MaybeRc<T>`) or already built (`MaterializedHandle<T>`).
+pub trait MaybeShared<T> {
+    /// Creates a new `Weak<T>` pointer to this allocation.
+    fn downgrade(&self) -> Weak<T>;
+
+    /// Returns a raw pointer to the value of this allocation.
+    fn as_ptr(&self) -> *const T;
+}
+
+/// A handle to an already materialized `Rc<T>`
+///
+/// Behaves like `MaybeRc<T>` except that its `Weak<T>` references can be upgraded right away.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::{MaterializedHandle, MaybeShared};
+///
+/// struct Child {
+///     parent: Weak<usize>,
+/// }
+///
+/// fn new_child(parent: &impl MaybeShared<usize>) -> Child {
+///     Child { parent: parent.downgrade() }
+/// }
+///
+/// let handle = MaterializedHandle::new(Rc::new(42));
+/// let child = new_child(&handle);
+/// assert_eq!(child.parent.upgrade().map(|e| *e), Some(42));
+/// ```
+pub struct MaterializedHandle<T> {
+    rc: Rc<T>,
+}
+
+impl<T> MaterializedHandle<T> {
+    /// Constructs a new `MaterializedHandle<T>` from an existing `Rc<T>`.
+    pub fn new(rc: Rc<T>) -> Self {
+        Self { rc }
+    }
+
+    /// Returns the number of `Weak<T>` references to this allocation.
+    pub fn weak_count(&self) -> usize {
+        Rc::weak_count(&self.rc)
+    }
+
+    /// Consumes this handle returning the inner `Rc<T>`.
+    pub fn into_rc(self) -> Rc<T> {
+        self.rc
+    }
+}
+
+impl<T> From<Rc<T>> for MaterializedHandle<T> {
+    fn from(rc: Rc<T>) -> Self {
+        Self::new(rc)
+    }
+}
+
+impl<T> MaybeShared<T> for MaterializedHandle<T> {
+    fn downgrade(&self) -> Weak<T> {
+        Rc::downgrade(&self.rc)
+    }
+
+    fn as_ptr(&self) -> *const T {
+        Rc::as_ptr(&self.rc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Child {
+        parent: Weak<usize>,
+    }
+
+    fn new_child<S: MaybeShared<usize>>(parent: &S) -> Child {
+        let child = Child { parent: parent.downgrade() };
+        assert_eq!(child.parent.as_ptr(), parent.as_ptr(), "Weak and handle point to a different objects");
+        child
+    }
+
+    #[test]
+    fn test_materialized() {
+        let handle = MaterializedHandle::new(Rc::new(42));
+        let child = new_child(&handle);
+
+        assert_eq!(handle.weak_count(), 1, "weak must be counted");
+        assert_eq!(child.parent.upgrade().map(|e| *e), Some(42), "must be upgradable");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_maybe() {
+        use crate::MaybeRc;
+
+        let maybe = MaybeRc::new();
+        let child = new_child(&maybe);
+        assert!(child.parent.upgrade().is_none(), "must not be upgradable");
+
+        let rc = maybe.materialize(42);
+        assert_eq!(child.parent.upgrade().map(|e| *e), Some(42), "must be upgradable");
+        drop(rc);
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// A `Weak<T>` reference that can tell if its allocation was materialized
+///
+/// Useful for debugging graph construction as its `Debug` implementation prints
+/// `MaybeWeak { materialized: false }` until the value is materialized without
+/// requiring `T: Debug` and without upgrading the reference.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Weak;
+/// use maybe_rc::{try_new_cyclic_rc, MaybeWeak};
+///
+/// struct Node(Weak<Node>);
+///
+/// let rc = try_new_cyclic_rc(|weak| {
+///     let maybe = MaybeWeak::from(weak.clone());
+///     assert_eq!(format!("{:?}", maybe), "MaybeWeak { materialized: false }");
+///     Ok::<_, ()>(Node(weak.clone()))
+/// }).unwrap();
+///
+/// let maybe = MaybeWeak::from(rc.0.clone());
+/// assert_eq!(format!("{:?}", maybe), "MaybeWeak { materialized: true }");
+/// ```
+pub struct MaybeWeak<T> {
+    weak: Weak<T>,
+}
+
+impl<T> MaybeWeak<T> {
+    /// Returns `true` if the value is materialized and still alive.
+    pub fn is_materialized(&self) -> bool {
+        self.weak.strong_count() > 0
+    }
+
+    /// Attempts to upgrade to an `Rc<T>`, same as `Weak<T>::upgrade`.
+    pub fn upgrade(&self) -> Option<Rc<T>> {
+        self.weak.upgrade()
+    }
+
+    /// Consumes this wrapper returning the inner `Weak<T>`.
+    pub fn into_weak(self) -> Weak<T> {
+        self.weak
+    }
+}
+
+impl<T> From<Weak<T>> for MaybeWeak<T> {
+    fn from(weak: Weak<T>) -> Self {
+        Self { weak }
+    }
+}
+
+impl<T> Clone for MaybeWeak<T> {
+    fn clone(&self) -> Self {
+        Self { weak: self.weak.clone() }
+    }
+}
+
+impl<T> fmt::Debug for MaybeWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MaybeWeak")
+            .field("materialized", &self.is_materialized())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::try_new_cyclic_rc;
+
+    #[test]
+    fn test_debug() {
+        struct NotDebug(Weak<NotDebug>);
+
+        let mut pending = None;
+        let rc = try_new_cyclic_rc(|weak| {
+            pending = Some(format!("{:?}", MaybeWeak::from(weak.clone())));
+            Ok::<_, ()>(NotDebug(weak.clone()))
+        }).unwrap();
+
+        let weak = MaybeWeak::from(rc.0.clone());
+        assert_eq!(pending.as_deref(), Some("MaybeWeak { materialized: false }"), "must not be materialized");
+        assert_eq!(format!("{:?}", weak), "MaybeWeak { materialized: true }", "must be materialized");
+
+        drop(rc);
+        assert_eq!(format!("{:?}", weak), "MaybeWeak { materialized: false }", "must not be alive");
+    }
+
+    #[test]
+    fn test_no_upgrade() {
+        let rc = Rc::new(42);
+        let weak = MaybeWeak::from(Rc::downgrade(&rc));
+
+        let _ = format!("{:?}", weak);
+        assert_eq!(Rc::strong_count(&rc), 1, "must not be upgraded");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+    }
+}
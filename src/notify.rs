@@ -0,0 +1,209 @@
+use std::cell::{Cell, RefCell};
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+use crate::MaybeRc;
+
+#[derive(Default)]
+struct Shared {
+    done: Cell<bool>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl Shared {
+    fn finish(&self) {
+        self.done.set(true);
+        // wakers are taken out first as waking might register new ones
+        let wakers = self.wakers.take();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// A `MaybeRc<T>` whose weak references can be awaited until it is materialized
+///
+/// Awaiting a `NotifyWeak<T>` resolves once this node is either materialized or abandoned:
+///
+/// - materialized: resolves to `Some(Rc<T>)`, or `None` if the node was already dropped by the time the task is polled;
+/// - abandoned (dropped without materialization): resolves to `None`.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::NotifyMaybeRc;
+///
+/// async fn wait(maybe: NotifyMaybeRc<usize>) -> Option<usize> {
+///     let weak = maybe.downgrade();
+///     let _rc = maybe.materialize(42);
+///     weak.await.map(|rc| *rc)
+/// }
+/// ```
+pub struct NotifyMaybeRc<T> {
+    maybe: Option<MaybeRc<T>>,
+    shared: Rc<Shared>,
+}
+
+/// A `Weak<T>` reference created by `NotifyMaybeRc<T>` which can be awaited
+pub struct NotifyWeak<T> {
+    weak: Weak<T>,
+    shared: Rc<Shared>,
+}
+
+/// Future returned by `NotifyWeak<T>::into_future`
+pub struct NotifyFuture<T> {
+    weak: Weak<T>,
+    shared: Rc<Shared>,
+}
+
+impl<T> NotifyMaybeRc<T> {
+    /// Constructs a new `NotifyMaybeRc<T>`.
+    pub fn new() -> Self {
+        Self { maybe: Some(MaybeRc::new()), shared: Rc::default() }
+    }
+
+    /// Creates a new `NotifyWeak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> NotifyWeak<T> {
+        NotifyWeak {
+            weak: self.maybe.as_ref().expect("MaybeRc is only taken when consumed").downgrade(),
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Materialize this allocation to a fully-contructed `Rc<T>` and wake all waiting tasks.
+    pub fn materialize(mut self, value: T) -> Rc<T> {
+        let rc = self.maybe.take().expect("MaybeRc is only taken when consumed").materialize(value);
+        self.shared.finish();
+        rc
+    }
+}
+
+impl<T> Default for NotifyMaybeRc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for NotifyMaybeRc<T> {
+    // node is abandoned, waiting tasks resolve to `None`
+    fn drop(&mut self) {
+        if !self.shared.done.get() {
+            drop(self.maybe.take());
+            self.shared.finish();
+        }
+    }
+}
+
+impl<T> NotifyWeak<T> {
+    /// Attempts to upgrade to an `Rc<T>` without waiting, same as `Weak<T>::upgrade`.
+    pub fn upgrade(&self) -> Option<Rc<T>> {
+        self.weak.upgrade()
+    }
+
+    /// Consumes this reference returning the inner `Weak<T>`.
+    pub fn into_weak(self) -> Weak<T> {
+        self.weak
+    }
+}
+
+impl<T> Clone for NotifyWeak<T> {
+    fn clone(&self) -> Self {
+        Self { weak: self.weak.clone(), shared: self.shared.clone() }
+    }
+}
+
+impl<T> IntoFuture for NotifyWeak<T> {
+    type Output = Option<Rc<T>>;
+    type IntoFuture = NotifyFuture<T>;
+
+    fn into_future(self) -> NotifyFuture<T> {
+        NotifyFuture { weak: self.weak, shared: self.shared }
+    }
+}
+
+impl<T> Future for NotifyFuture<T> {
+    type Output = Option<Rc<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Rc<T>>> {
+        if self.shared.done.get() {
+            return Poll::Ready(self.weak.upgrade());
+        }
+
+        let mut wakers = self.shared.wakers.borrow_mut();
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn poll<F: Future + Unpin>(future: &mut F, waker: &Arc<CountingWaker>) -> Poll<F::Output> {
+        let waker = Waker::from(waker.clone());
+        Pin::new(future).poll(&mut Context::from_waker(&waker))
+    }
+
+    #[test]
+    fn test_await_materialized() {
+        let waker = Arc::new(CountingWaker::default());
+        let maybe = NotifyMaybeRc::new();
+        let mut future = maybe.downgrade().into_future();
+
+        assert!(poll(&mut future, &waker).is_pending(), "must wait for materialization");
+        assert!(poll(&mut future, &waker).is_pending(), "must wait for materialization");
+
+        let rc = maybe.materialize(42);
+        assert_eq!(waker.0.load(Ordering::SeqCst), 1, "task must be woken exactly once");
+
+        match poll(&mut future, &waker) {
+            Poll::Ready(Some(node)) => assert!(Rc::ptr_eq(&node, &rc), "must resolve to the materialized node"),
+            _ => panic!("must resolve to the materialized node"),
+        }
+    }
+
+    #[test]
+    fn test_await_abandoned() {
+        let waker = Arc::new(CountingWaker::default());
+        let maybe = NotifyMaybeRc::<usize>::new();
+        let mut future = maybe.downgrade().into_future();
+
+        assert!(poll(&mut future, &waker).is_pending(), "must wait for materialization");
+
+        drop(maybe);
+        assert_eq!(waker.0.load(Ordering::SeqCst), 1, "task must be woken on abandon");
+        assert!(matches!(poll(&mut future, &waker), Poll::Ready(None)), "abandoned node must resolve to None");
+    }
+
+    #[test]
+    fn test_await_after_materialized() {
+        let waker = Arc::new(CountingWaker::default());
+        let maybe = NotifyMaybeRc::new();
+        let weak = maybe.downgrade();
+        let rc = maybe.materialize(42);
+
+        let mut future = weak.clone().into_future();
+        assert!(matches!(poll(&mut future, &waker), Poll::Ready(Some(_))), "must resolve right away");
+
+        drop(rc);
+        let mut future = weak.into_future();
+        assert!(matches!(poll(&mut future, &waker), Poll::Ready(None)), "dropped node must resolve to None");
+    }
+}
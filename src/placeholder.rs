@@ -0,0 +1,155 @@
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// A `MaybeRc<T>` whose weak references upgrade to a placeholder value until it is materialized
+///
+/// Consumers of `PlaceholderWeak<T>` don't have to special-case the not-ready state:
+/// before materialization they get a shared `T::default()` placeholder, afterwards the real node.
+///
+/// The placeholder is a separate allocation shared by all weak references of this node,
+/// so it is never the same `Rc<T>` as the materialized node. Changes to the placeholder
+/// (e.g. through `RefCell`) are not carried over to the real node.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::PlaceholderMaybeRc;
+///
+/// let maybe = PlaceholderMaybeRc::<usize>::new();
+/// let weak = maybe.downgrade();
+/// assert_eq!(weak.upgrade().map(|e| *e), Some(0));
+///
+/// let rc = maybe.materialize(42);
+/// assert_eq!(weak.upgrade().map(|e| *e), Some(42));
+/// ```
+pub struct PlaceholderMaybeRc<T> {
+    maybe: MaybeRc<T>,
+    placeholder: Rc<T>,
+    // alive while the node is pending, copies of the placeholder can outlive that
+    pending: Rc<()>,
+}
+
+/// Weak reference created by `PlaceholderMaybeRc<T>`
+pub struct PlaceholderWeak<T> {
+    node: Weak<T>,
+    placeholder: Weak<T>,
+    pending: Weak<()>,
+}
+
+impl<T: Default> PlaceholderMaybeRc<T> {
+    /// Constructs a new `PlaceholderMaybeRc<T>` with a `T::default()` placeholder.
+    pub fn new() -> Self {
+        Self { maybe: MaybeRc::new(), placeholder: Rc::new(T::default()), pending: Rc::new(()) }
+    }
+}
+
+impl<T> PlaceholderMaybeRc<T> {
+    /// Creates a new `PlaceholderWeak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> PlaceholderWeak<T> {
+        PlaceholderWeak {
+            node: self.maybe.downgrade(),
+            placeholder: Rc::downgrade(&self.placeholder),
+            pending: Rc::downgrade(&self.pending),
+        }
+    }
+
+    /// Materialize this allocation to a fully-contructed `Rc<T>`.
+    ///
+    /// All `PlaceholderWeak<T>` references upgrade to the real node after this method finishes.
+    /// The placeholder is dropped unless upgraded copies of it are still alive.
+    pub fn materialize(self, value: T) -> Rc<T> {
+        self.maybe.materialize(value)
+    }
+}
+
+impl<T: Default> Default for PlaceholderMaybeRc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PlaceholderWeak<T> {
+    /// Upgrades to the real node if it was materialized or to the placeholder while it is pending.
+    ///
+    /// Returns `None` once the node was dropped (or abandoned), even if copies of the placeholder are alive.
+    pub fn upgrade(&self) -> Option<Rc<T>> {
+        match self.node.upgrade() {
+            Some(node) => Some(node),
+            None if self.pending.strong_count() > 0 => self.placeholder.upgrade(),
+            None => None,
+        }
+    }
+
+    /// Returns `true` if this reference upgrades to the real node.
+    pub fn is_materialized(&self) -> bool {
+        self.node.strong_count() > 0
+    }
+
+    /// Returns the `Weak<T>` reference to the real node.
+    pub fn into_weak(self) -> Weak<T> {
+        self.node
+    }
+}
+
+impl<T> Clone for PlaceholderWeak<T> {
+    fn clone(&self) -> Self {
+        Self { node: self.node.clone(), placeholder: self.placeholder.clone(), pending: self.pending.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholder_before_materialize() {
+        let maybe = PlaceholderMaybeRc::<String>::new();
+        let first = maybe.downgrade();
+        let second = first.clone();
+
+        let a = first.upgrade().expect("must upgrade to the placeholder");
+        let b = second.upgrade().expect("must upgrade to the placeholder");
+        assert_eq!(*a, "", "placeholder must be the default value");
+        assert!(Rc::ptr_eq(&a, &b), "placeholder must be shared");
+        assert!(!first.is_materialized(), "must not be materialized");
+
+        let rc = maybe.materialize(String::from("node"));
+        let real = first.upgrade().expect("must upgrade to the real node");
+        assert!(Rc::ptr_eq(&real, &rc), "must upgrade to the real node");
+        assert!(!Rc::ptr_eq(&real, &a), "real node must not be the placeholder");
+        assert!(first.is_materialized(), "must be materialized");
+    }
+
+    #[test]
+    fn test_placeholder_dropped() {
+        let maybe = PlaceholderMaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.materialize(42);
+        drop(rc);
+        assert!(weak.upgrade().is_none(), "dropped node must not upgrade to the placeholder");
+
+        let maybe = PlaceholderMaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+        drop(maybe);
+        assert!(weak.upgrade().is_none(), "abandoned node must not be upgradable");
+    }
+
+    #[test]
+    fn test_placeholder_copy_outlives_node() {
+        let maybe = PlaceholderMaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+        let copy = weak.upgrade().expect("must upgrade to the placeholder");
+
+        drop(maybe.materialize(42));
+        assert!(weak.upgrade().is_none(), "dropped node must not upgrade to the placeholder copy");
+
+        let maybe = PlaceholderMaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+        let abandoned_copy = weak.upgrade().expect("must upgrade to the placeholder");
+        drop(maybe);
+        assert!(weak.upgrade().is_none(), "abandoned node must not upgrade to the placeholder copy");
+        assert_eq!((*copy, *abandoned_copy), (0, 0), "copies must stay usable");
+    }
+}
@@ -1,5 +1,22 @@
-use std::mem::MaybeUninit;
-use std::rc::{Rc, Weak};
+use std::alloc::{Allocator, Global, Layout};
+use std::async_iter::AsyncIterator;
+use std::convert::TryFrom;
+use std::future;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::ptr;
+use std::rc::{Rc, UniqueRc, Weak};
+#[cfg(feature = "debug-registry")]
+use std::time::Duration;
+
+#[cfg(feature = "debug-registry")]
+use crate::debug::{Tracker, TrackedRc};
+#[cfg(feature = "debug-locations")]
+use crate::locations::Origins;
+use crate::critical;
+use crate::drop_guard::{DropGuard, PrefixGuard};
+use crate::MaybeShared;
 
 /// An uninitialized version of `Rc<T>`
 ///
@@ -42,61 +59,547 @@ use std::rc::{Rc, Weak};
 ///     }
 /// }
 /// ```
-pub struct MaybeRc<T> {
-    weak: Weak<T>,
+///
+/// Only sized values are supported, unsized ones are rejected at compile time:
+///
+/// ```compile_fail
+/// use std::fmt::Debug;
+/// use maybe_rc::MaybeRc;
+///
+/// let maybe = MaybeRc::<dyn Debug>::new();
+/// ```
+///
+/// The backing allocation can be placed in a custom allocator with `MaybeRc::new_in`,
+/// e.g. a slot of a `Slab<T>`.
+pub struct MaybeRc<T, A: Allocator = Global> {
+    unique: UniqueRc<MaybeUninit<T>, A>,
+    #[cfg(feature = "debug-registry")]
+    tracker: Tracker,
+    #[cfg(feature = "debug-locations")]
+    _origins: Origins,
 }
 
 impl<T> MaybeRc<T> {
     /// Constructs a new `MaybeRc<T>`.
     pub fn new() -> Self {
-        let strong = Rc::new(MaybeUninit::<T>::uninit());
+        Self::new_in(Global)
+    }
+}
 
-        // SAFETY: `MaybeUninit` is [repr(transparent)] so it can
-        // be `stripped` down as memory layout should be the same
-        let weak = unsafe {
-            Weak::from_raw(Rc::downgrade(&strong).into_raw().cast())
-        };
+impl<T, A: Allocator + Clone> MaybeRc<T, A> {
+    /// Constructs a new `MaybeRc<T, A>` in the provided allocator.
+    ///
+    /// The allocation is made right away, so `Weak<T, A>` references and the materialized
+    /// `Rc<T, A>` all share it.
+    pub fn new_in(alloc: A) -> Self {
+        let unique = UniqueRc::new_in(MaybeUninit::uninit(), alloc);
+        #[cfg(feature = "debug-locations")]
+        let origins = Origins::new(&*unique);
+        #[cfg(feature = "debug-registry")]
+        let tracker = Tracker::new(&*unique as *const MaybeUninit<T>);
 
-        Self { weak }
+        Self {
+            unique,
+            #[cfg(feature = "debug-registry")]
+            tracker,
+            #[cfg(feature = "debug-locations")]
+            _origins: origins,
+        }
     }
 
     /// Creates a new `Weak<T>` pointer to this allocation.
     ///
     /// Upgrading this `Weak<T>` reference will fail and result in a None unless
     /// it is called after `MaybeRc<T>::materialize` finishes.
-    pub fn downgrade(&self) -> Weak<T> {
-        self.weak.clone()
+    ///
+    /// With the `debug-locations` feature the call site is recorded, see `locations::weak_origin`.
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn downgrade(&self) -> Weak<T, A> {
+        let (ptr, alloc) = UniqueRc::downgrade(&self.unique).into_raw_with_allocator();
+        #[cfg(feature = "debug-locations")]
+        crate::locations::record(ptr, std::panic::Location::caller());
+
+        // SAFETY: `MaybeUninit` is [repr(transparent)] so it can
+        // be `stripped` down as memory layout should be the same
+        unsafe {
+            Weak::from_raw_in(ptr.cast(), alloc)
+        }
+    }
+
+    /// Returns a raw pointer to the (uninitialized) value of this allocation.
+    ///
+    /// The allocation never moves, so the pointer is the same for all `Weak<T>` references
+    /// and for `Rc::as_ptr` after materialization until the allocation is freed.
+    pub fn as_ptr(&self) -> *const T {
+        let ptr: *const MaybeUninit<T> = &*self.unique;
+        ptr.cast()
+    }
+
+    /// Returns the size in bytes of the backing allocation, including the reference counts.
+    ///
+    /// Layout of std's `RcBox` is not public, so this is an approximation assuming
+    /// two `usize` counters placed before the value, which matches current std.
+    pub fn allocation_size(&self) -> usize {
+        let (layout, _) = Layout::new::<[usize; 2]>()
+            .extend(Layout::new::<T>())
+            .expect("allocation size overflow");
+        layout.pad_to_align().size()
     }
 
     /// Materialize this allocation to a fully-contructed `Rc<T>`.
     ///
     /// All `Weak<T>` references can be upgraded after this method finishes.
-    pub fn materialize(self, value: T) -> Rc<T> {
-        let ptr = self.weak.into_raw();
+    pub fn materialize(mut self, value: T) -> Rc<T, A> {
+        self.unique.write(value);
+        // SAFETY: value was written just above
+        unsafe { self.into_rc() }
+    }
+
+    /// Materialize this allocation as a leaf node that is not referenced by any `Weak<T>`.
+    ///
+    /// In debug builds panics if a `Weak<T>` to this allocation exists, which usually means
+    /// that a supposedly-leaf node was wired by mistake. Same as `materialize` in release builds.
+    pub fn materialize_leaf(self, value: T) -> Rc<T, A> {
+        let rc = self.materialize(value);
+        // weak count is not observable before materialization
+        debug_assert_eq!(Rc::weak_count(&rc), 0, "leaf node must not have weak references");
+        rc
+    }
+
+    /// Materialize this allocation to a fully-contructed `Rc<T>` if `T` is not bigger than `LIMIT` bytes.
+    ///
+    /// The size is checked at compile time, so oversized values fail to build:
+    ///
+    /// ```compile_fail
+    /// use maybe_rc::MaybeRc;
+    ///
+    /// MaybeRc::new().materialize_bounded::<16>([0u8; 32]);
+    /// ```
+    ///
+    /// ```
+    /// use maybe_rc::MaybeRc;
+    ///
+    /// MaybeRc::new().materialize_bounded::<16>([0u8; 16]);
+    /// ```
+    pub fn materialize_bounded<const LIMIT: usize>(self, value: T) -> Rc<T, A> {
+        const { assert!(mem::size_of::<T>() <= LIMIT, "value is bigger than the configured limit") };
+        self.materialize(value)
+    }
+
+    /// Materialize this allocation and get exclusive access to the value before sharing it.
+    ///
+    /// `f` is called while the allocation is still unique, so its changes are visible
+    /// to all `Weak<T>` references as soon as they can be upgraded.
+    ///
+    /// If `f` panics the value is dropped and `Weak<T>` references will never be upgradable.
+    ///
+    /// Materialization can't be reentered as it consumes `self`, code running inside `f`
+    /// (or while constructing the value) that upgrades `Weak<T>` references gets `None`.
+    pub fn materialize_then<F>(mut self, value: T, f: F) -> Rc<T, A>
+        where
+            F: FnOnce(&mut T),
+    {
+        self.unique.write(value);
+
+        // SAFETY: value was written just above
+        let mut guard = unsafe { DropGuard::new(&mut self.unique) };
+        f(guard.value());
+        guard.disarm();
+
+        // SAFETY: value was written just above
+        unsafe { self.into_rc() }
+    }
+
+    /// SAFETY: value must be initialized
+    unsafe fn into_rc(self) -> Rc<T, A> {
+        // dropping the tracker unregisters the node, only `materialize_tracked` keeps tracking it
+        #[cfg(feature = "debug-registry")]
+        drop(self.tracker);
+        let unique = self.unique;
+
+        // SAFETY: value was written by the caller
+        critical::section(|| unsafe {
+            UniqueRc::into_rc(unique).assume_init()
+        })
+    }
+}
+
+impl<T> MaybeRc<T> {
+    /// Materialize this allocation by copying a `Copy` value straight into it.
+    ///
+    /// The value is copied with `ptr::copy_nonoverlapping` from `value` into the allocation.
+    /// Performs the same as `materialize(*value)` as the compiler already elides the temporary,
+    /// it only saves the dereference when the value is borrowed.
+    pub fn materialize_copied(mut self, value: &T) -> Rc<T>
+        where
+            T: Copy,
+    {
+        // SAFETY: source is a valid reference and destination is a separate unique allocation
+        unsafe {
+            ptr::copy_nonoverlapping(value, self.unique.as_mut_ptr(), 1);
+            self.into_rc()
+        }
+    }
+
+    /// Materialize this allocation reserving `extra` strong references for the caller.
+    ///
+    /// The returned `Rc<T>` starts with a strong count of `1 + extra`. Each reserved reference
+    /// can later be turned into an `Rc<T>` with `unsafe { Rc::from_raw(Rc::as_ptr(&rc)) }`,
+    /// which is useful for FFI handing out raw pointers without cloning first.
+    ///
+    /// Every reservation must eventually be balanced by such `Rc::from_raw` (or
+    /// `Rc::decrement_strong_count`), otherwise the value is leaked.
+    pub fn materialize_reserved(self, value: T, extra: usize) -> Rc<T> {
+        let rc = self.materialize(value);
+        for _ in 0..extra {
+            // SAFETY: `rc` is alive so the strong count is at least one
+            unsafe { Rc::increment_strong_count(Rc::as_ptr(&rc)) };
+        }
+        rc
+    }
+
+    /// Materialize this allocation and expect the returned handle to be dropped within `lifetime`.
+    ///
+    /// Handles that stay alive for longer (e.g. forgotten with `mem::forget` or kept alive
+    /// by a strong cycle) are reported by `debug::leaked_nodes`.
+    #[cfg(feature = "debug-registry")]
+    pub fn materialize_tracked(self, value: T, lifetime: Duration) -> TrackedRc<T> {
+        TrackedRc::new(self.materialize(value), lifetime)
+    }
+
+    /// Materialize this allocation with a value built by `init` from the final pointer to it.
+    ///
+    /// The allocation never moves, so `init` can embed raw pointers to the value
+    /// (or its fields) into the value itself. They stay valid as long as the value is not
+    /// moved out of the `Rc<T>`, e.g. with `Rc::try_unwrap`, `Rc::into_inner` or through
+    /// `Rc::get_mut`. The pointer must not be dereferenced inside `init`.
+    pub fn materialize_self_ptr<F>(self, init: F) -> Rc<T>
+        where
+            F: FnOnce(*const T) -> T,
+    {
+        let value = init(self.as_ptr());
+        self.materialize(value)
+    }
+
+    /// Materialize this allocation with a value built by `f` which might fail.
+    ///
+    /// `f` receives a `Weak<T>` to this allocation for wiring. On error this `MaybeRc<T>`
+    /// is dropped and a `Weak<T>` to the abandoned allocation is returned with the error,
+    /// so callers tracking nodes by their weak references can identify which one failed.
+    /// Such weak references are never upgradable and the allocation is freed once all of them drop.
+    pub fn try_materialize_with_weak<F, E>(self, f: F) -> Result<Rc<T>, (Weak<T>, E)>
+        where
+            F: FnOnce(&Weak<T>) -> Result<T, E>,
+    {
+        let weak = self.downgrade();
+        match f(&weak) {
+            Ok(value) => Ok(self.materialize(value)),
+            Err(e) => Err((weak, e)),
+        }
+    }
+
+    /// Materialize this allocation with a value built from all dependencies produced by `deps`.
+    ///
+    /// The stream is collected first, `Weak<T>` references stay non-upgradable across its awaits.
+    /// `init` receives a `Weak<T>` to this allocation and the collected dependencies.
+    pub async fn materialize_from_stream<S, F>(self, init: F, deps: S) -> Rc<T>
+        where
+            S: AsyncIterator,
+            F: FnOnce(&Weak<T>, Vec<S::Item>) -> T,
+    {
+        let mut deps = std::pin::pin!(deps);
+        let mut collected = Vec::new();
+        while let Some(dep) = future::poll_fn(|cx| deps.as_mut().poll_next(cx)).await {
+            collected.push(dep);
+        }
+
+        let value = init(&self.downgrade(), collected);
+        self.materialize(value)
+    }
+
+    /// Materialize this allocation with a value built by `f` which might fail, dropping it on error.
+    ///
+    /// `f` receives a `Weak<T>` to this allocation for wiring. On error the allocation is
+    /// dropped and only the error is returned, all `Weak<T>` references stay non-upgradable.
+    /// Unlike `try_materialize_with_weak` nothing identifying the failed node is handed back,
+    /// so this suits callers that just propagate the error.
+    ///
+    /// The allocation is freed as soon as the last of those `Weak<T>` references drops,
+    /// use `materialize_or_invalidate` to learn how many of them are left.
+    pub fn try_materialize_or_drop<F, E>(self, f: F) -> Result<Rc<T>, E>
+        where
+            F: FnOnce(&Weak<T>) -> Result<T, E>,
+    {
+        let value = f(&self.downgrade())?;
+        Ok(self.materialize(value))
+    }
+
+    /// Materialize this allocation with a value built by `f`, invalidating it on error.
+    ///
+    /// Same as `try_materialize_or_drop` but the error comes together with the number of
+    /// `Weak<T>` references that are now permanently dangling. The allocation is freed
+    /// as soon as all of them drop.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use maybe_rc::MaybeRc;
+    ///
+    /// let maybe = MaybeRc::<usize>::new();
+    /// let weak = maybe.downgrade();
+    ///
+    /// let (dangling, error) = maybe.materialize_or_invalidate(|_| Err("failed")).unwrap_err();
+    /// assert_eq!((dangling, error), (1, "failed"));
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn materialize_or_invalidate<F, E>(self, f: F) -> Result<Rc<T>, (usize, E)>
+        where
+            F: FnOnce(&Weak<T>) -> Result<T, E>,
+    {
+        let weak = self.downgrade();
+        let e = match f(&weak) {
+            Ok(value) => return Ok(self.materialize(value)),
+            Err(e) => e,
+        };
+        drop(weak);
+
+        let unique = self.unique;
+        // SAFETY: `Rc<T>` is not shared between threads and no user code runs before
+        // the strong reference drops, so weak references can't observe the uninitialized value
+        let dangling = critical::section(|| {
+            let rc = UniqueRc::into_rc(unique);
+            Rc::weak_count(&rc)
+        });
+        Err((dangling, e))
+    }
+
+    /// Materialize this allocation and hand back the scratch state used to construct the value.
+    ///
+    /// This is a plain passthrough for `scratch` that gives construction-only state
+    /// a place to flow out of the builder so it can be reused for the next node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use maybe_rc::MaybeRc;
+    ///
+    /// let mut scratch = Vec::new();
+    /// let mut nodes = Vec::new();
+    ///
+    /// for words in [["a", "b"], ["c", "d"]] {
+    ///     scratch.clear();
+    ///     scratch.extend(words.iter().map(|word| word.to_uppercase()));
+    ///
+    ///     let (node, reclaimed) = MaybeRc::new().materialize_reclaim(scratch.join(" "), scratch);
+    ///     scratch = reclaimed;
+    ///     nodes.push(node);
+    /// }
+    ///
+    /// assert_eq!(*nodes[0], "A B");
+    /// assert_eq!(*nodes[1], "C D");
+    /// ```
+    pub fn materialize_reclaim<S>(self, value: T, scratch: S) -> (Rc<T>, S) {
+        (self.materialize(value), scratch)
+    }
+
+    /// Materialize this allocation with a value that was constructed in a `MaybeUninit<T>`.
+    ///
+    /// # Safety
+    ///
+    /// `value` must be fully initialized, same as for `MaybeUninit<T>::assume_init`.
+    pub unsafe fn materialize_assume_init(mut self, value: MaybeUninit<T>) -> Rc<T> {
+        *self.unique = value;
+        // SAFETY: value was written just above
+        unsafe { self.into_rc() }
+    }
+
+    /// Materialize this allocation checking that the value's self-weak points to itself.
+    ///
+    /// In debug builds panics if the `Weak<T>` returned by `get_self_weak` points to
+    /// a different allocation, which usually means it was wired to the wrong node.
+    /// Same as `materialize` in release builds.
+    pub fn materialize_verify_self<F>(self, value: T, get_self_weak: F) -> Rc<T>
+        where
+            F: Fn(&T) -> &Weak<T>,
+    {
+        let rc = self.materialize(value);
+        debug_assert!(get_self_weak(&rc).as_ptr() == Rc::as_ptr(&rc), "self weak must point to the materialized value");
+        rc
+    }
+
+    /// Materialize this allocation into a still unique `UniqueMaybeRc<T>`.
+    ///
+    /// The value can be mutated until it is shared with `UniqueMaybeRc::share`,
+    /// `Weak<T>` references can't be upgraded until then.
+    pub fn materialize_unique(mut self, value: T) -> UniqueMaybeRc<T> {
+        self.unique.write(value);
+        UniqueMaybeRc { maybe: ManuallyDrop::new(self) }
+    }
+
+    /// Materialize this allocation into a pinned and still unique `UniqueMaybeRc<T>`.
+    ///
+    /// Useful for intrusive structures that need to store pointers into the value
+    /// before it is shared with `UniqueMaybeRc::share_pinned`. While the handle is unique
+    /// no `Weak<T>` can be upgraded, so the pinned mutable access can't be aliased.
+    ///
+    /// # Safety
+    ///
+    /// `Weak<T>` references to this allocation can be upgraded to unpinned `Rc<T>` after sharing.
+    /// Caller must guarantee that the value is never moved out of them (e.g. with
+    /// `Rc::try_unwrap` or `Rc::get_mut` and `mem::swap`) unless `T: Unpin`.
+    pub unsafe fn materialize_pinned_unique(self, value: T) -> Pin<UniqueMaybeRc<T>> {
+        Pin::new_unchecked(self.materialize_unique(value))
+    }
 
-        // SAFETY: this value was not initialized before so
-        // we need to update it without dropping the old value
+}
+
+/// A materialized `Rc<T>` which is still unique and allows mutable access to the value
+///
+/// Created by `MaybeRc::materialize_unique`. `Weak<T>` references can't be upgraded
+/// until it is converted into `Rc<T>` with `UniqueMaybeRc::share`. Dropping it
+/// drops the value and leaves all `Weak<T>` references non-upgradable.
+pub struct UniqueMaybeRc<T> {
+    maybe: ManuallyDrop<MaybeRc<T>>,
+}
+
+impl<T> UniqueMaybeRc<T> {
+    /// Converts this handle into a shared `Rc<T>`.
+    ///
+    /// All `Weak<T>` references can be upgraded after this function finishes.
+    pub fn share(this: Self) -> Rc<T> {
+        let mut this = ManuallyDrop::new(this);
+
+        // SAFETY: `this` is never used again and its `Drop` is suppressed
+        let maybe = unsafe { ManuallyDrop::take(&mut this.maybe) };
+
+        // SAFETY: value is always initialized for `UniqueMaybeRc`
+        unsafe { maybe.into_rc() }
+    }
+
+    /// Converts this pinned handle into a pinned shared `Rc<T>`.
+    pub fn share_pinned(this: Pin<Self>) -> Pin<Rc<T>> {
+        // SAFETY: allocation doesn't move when it is shared and pinned handles
+        // can only be created through unsafe `MaybeRc::materialize_pinned_unique`
         unsafe {
-            std::ptr::write(ptr.cast_mut(), value);
+            Pin::new_unchecked(Self::share(Pin::into_inner_unchecked(this)))
         }
+    }
+}
 
-        // SAFETY: we hold a weak reference so content is still allocated
-        // ASSUMPTION: we can restore `Rc` from strong count of 0
+impl<T> Deref for UniqueMaybeRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: value is always initialized for `UniqueMaybeRc`
         unsafe {
-            // increment strong count to 1, so weak can be upgraded
-            Rc::increment_strong_count(ptr);
+            self.maybe.unique.assume_init_ref()
         }
+    }
+}
 
-        // SAFETY: `UnsafeCell` with `MaybeUninit` are `#[repr(transparent)]` so they
-        // can be `stripped` down as memory layout should be the same
+impl<T> DerefMut for UniqueMaybeRc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: value is always initialized for `UniqueMaybeRc`
         unsafe {
-            // we can consume Weak and make Rc from it because
-            // at this point strong = 1 and weak = 1
-            Rc::from_raw(ptr.cast())
+            self.maybe.unique.assume_init_mut()
         }
     }
 }
 
+impl<T> Drop for UniqueMaybeRc<T> {
+    fn drop(&mut self) {
+        // SAFETY: `maybe` is never used again, owning it frees the allocation
+        // even if dropping the value panics
+        let mut maybe = unsafe { ManuallyDrop::take(&mut self.maybe) };
+
+        // SAFETY: value is always initialized for `UniqueMaybeRc`
+        unsafe {
+            maybe.unique.assume_init_drop();
+        }
+    }
+}
+
+impl<T, const N: usize> MaybeRc<[T; N]> {
+    /// Materialize this allocation with elements produced by `f` for every index in order.
+    ///
+    /// Elements are written straight into the allocation without building the array on the stack first.
+    /// If `f` panics, elements written so far are dropped, that bookkeeping is skipped for types without drop glue.
+    pub fn materialize_from_fn<F>(mut self, mut f: F) -> Rc<[T; N]>
+        where
+            F: FnMut(usize) -> T,
+    {
+        let slot = self.unique.as_mut_ptr().cast::<T>();
+
+        if mem::needs_drop::<T>() {
+            let mut guard = PrefixGuard::new(slot);
+            while guard.len < N {
+                // SAFETY: `guard.len < N` so the element is in bounds of the allocation
+                unsafe { slot.add(guard.len).write(f(guard.len)) };
+                guard.len += 1;
+            }
+            guard.disarm();
+        } else {
+            for index in 0..N {
+                // SAFETY: `index < N` so the element is in bounds of the allocation
+                unsafe { slot.add(index).write(f(index)) };
+            }
+        }
+
+        // SAFETY: all `N` elements were written just above
+        unsafe { self.into_rc() }
+    }
+
+    /// Materialize this allocation by copying all elements of `slice` straight into it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length of `slice` is not `N`.
+    pub fn materialize_from_slice(mut self, slice: &[T]) -> Rc<[T; N]>
+        where
+            T: Copy,
+    {
+        assert_eq!(slice.len(), N, "slice length {} doesn't match the slot length {}", slice.len(), N);
+
+        // SAFETY: both regions hold `N` elements and destination is a separate unique allocation
+        unsafe {
+            ptr::copy_nonoverlapping(slice.as_ptr(), self.unique.as_mut_ptr().cast::<T>(), N);
+            self.into_rc()
+        }
+    }
+
+    /// Materialize this allocation to an `Rc<[T]>` with elements moved out of `vec`.
+    ///
+    /// The backing buffer of `vec` is freed, elements are moved into this allocation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if length of `vec` is not `N`, before any element is moved.
+    pub fn materialize_from_vec(self, vec: Vec<T>) -> Rc<[T]> {
+        let array = match <[T; N]>::try_from(vec) {
+            Ok(array) => array,
+            Err(vec) => panic!("vector length {} doesn't match the slot length {}", vec.len(), N),
+        };
+        self.materialize(array)
+    }
+}
+
+impl<T> MaybeShared<T> for MaybeRc<T> {
+    fn downgrade(&self) -> Weak<T> {
+        MaybeRc::downgrade(self)
+    }
+
+    fn as_ptr(&self) -> *const T {
+        MaybeRc::as_ptr(self)
+    }
+}
+
+impl<T, A: Allocator + Clone> From<&MaybeRc<T, A>> for Weak<T, A> {
+    fn from(value: &MaybeRc<T, A>) -> Self {
+        value.downgrade()
+    }
+}
+
 impl<T> Default for MaybeRc<T> {
     fn default() -> Self {
         Self::new()
@@ -160,4 +663,446 @@ mod tests {
         drop(rc);
         assert!(weak.upgrade().is_none(), "must not be upgradable");
     }
+
+    #[test]
+    fn test_materialize_then() {
+        struct Node {
+            value: usize,
+            me: Weak<Node>,
+        }
+
+        let maybe = MaybeRc::<Node>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.materialize_then(Node { value: 1, me: Weak::new() }, |node| {
+            node.value = 42;
+            node.me = weak.clone();
+        });
+
+        assert_eq!(rc.value, 42, "value is not what was set in the callback");
+        assert_eq!(rc.me.as_ptr(), Rc::as_ptr(&rc), "Weak and Rc point to a different objects");
+    }
+
+    #[test]
+    fn test_allocation_size() {
+        let maybe = MaybeRc::<[u8; 3]>::new();
+        assert!(maybe.allocation_size() >= mem::size_of::<[u8; 3]>(), "must include the value");
+        assert_eq!(maybe.allocation_size() % mem::align_of::<usize>(), 0, "must be padded to the header alignment");
+
+        let maybe = MaybeRc::<u128>::new();
+        assert!(maybe.allocation_size() >= mem::size_of::<u128>() + 2 * mem::size_of::<usize>(), "must include the header");
+    }
+
+    #[test]
+    fn test_upgrade_in_materialize_then() {
+        let maybe = MaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.materialize_then(1, |value| {
+            assert!(weak.upgrade().is_none(), "must not be upgradable while materializing");
+            *value = 42;
+        });
+
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+        drop(rc);
+    }
+
+    #[test]
+    fn test_ptr_stable() {
+        let maybe = MaybeRc::<usize>::new();
+        let ptr = maybe.as_ptr();
+
+        let weaks: Vec<_> = (0..3).map(|_| maybe.downgrade()).collect();
+        assert_eq!(maybe.as_ptr(), ptr, "allocation must not move");
+        assert!(weaks.iter().all(|weak| weak.as_ptr() == ptr), "Weak and MaybeRc point to a different objects");
+
+        let rc = maybe.materialize(42);
+        assert_eq!(Rc::as_ptr(&rc), ptr, "allocation must not move");
+        assert!(weaks.iter().all(|weak| weak.as_ptr() == ptr), "Weak and Rc point to a different objects");
+    }
+
+    #[test]
+    fn test_materialize_assume_init() {
+        let maybe = MaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let mut value = MaybeUninit::uninit();
+        value.write(42);
+
+        // SAFETY: value was written just above
+        let rc = unsafe { maybe.materialize_assume_init(value) };
+
+        assert_eq!(*rc, 42, "value is not what was provided");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+    }
+
+    #[test]
+    fn test_try_materialize_or_drop_ok() {
+        let maybe = MaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.try_materialize_or_drop(|_| Ok::<_, ()>(42)).expect("must materialize");
+        assert_eq!(*rc, 42, "value is not what was provided");
+        assert!(weak.upgrade().is_some(), "must be upgradable");
+    }
+
+    #[test]
+    fn test_try_materialize_or_drop_err() {
+        let maybe = MaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let error = maybe.try_materialize_or_drop(|_| Err(42)).expect_err("must fail");
+        assert_eq!(error, 42, "error is not what was returned");
+        assert!(weak.upgrade().is_none(), "must not be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_or_invalidate_err() {
+        let maybe = MaybeRc::<usize>::new();
+        let weaks: Vec<_> = (0..3).map(|_| maybe.downgrade()).collect();
+
+        let (dangling, error) = maybe.materialize_or_invalidate(|_| Err(42)).expect_err("must fail");
+        assert_eq!(error, 42, "error is not what was returned");
+        assert_eq!(dangling, weaks.len(), "must report all outstanding weaks");
+        assert!(weaks.iter().all(|weak| weak.upgrade().is_none()), "must not be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_or_invalidate_ok() {
+        let maybe = MaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.materialize_or_invalidate(|_| Ok::<_, ()>(42)).expect("must materialize");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(*rc), "must be upgradable");
+    }
+
+    #[test]
+    fn test_try_materialize_with_weak_ok() {
+        struct Node(usize, Weak<Node>);
+
+        let maybe = MaybeRc::<Node>::new();
+        let rc = maybe.try_materialize_with_weak(|weak| Ok::<_, ()>(Node(42, weak.clone())));
+
+        let rc = rc.expect("must not fail");
+        assert_eq!(rc.0, 42, "value is not what was provided");
+        assert_eq!(rc.1.as_ptr(), Rc::as_ptr(&rc), "Weak and Rc point to a different objects");
+    }
+
+    #[test]
+    fn test_try_materialize_with_weak_err() {
+        let maybe = MaybeRc::<usize>::new();
+        let ptr = maybe.as_ptr();
+        let other = maybe.downgrade();
+
+        let result = maybe.try_materialize_with_weak(|_| Err(42));
+
+        let (weak, error) = result.expect_err("must fail");
+        assert_eq!(error, 42, "incorrect error value");
+        assert_eq!(weak.as_ptr(), ptr, "Weak must point to the abandoned allocation");
+        assert!(weak.upgrade().is_none(), "must not be upgradable");
+        assert!(other.upgrade().is_none(), "must not be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_copied() {
+        let value: [u64; 64] = std::array::from_fn(|index| index as u64 * 3);
+
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+        let copied = maybe.materialize_copied(&value);
+        let moved = MaybeRc::new().materialize(value);
+
+        assert_eq!(*copied, *moved, "copied value differs from the generic path");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(value), "must be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_from_vec() {
+        let maybe = MaybeRc::<[String; 3]>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.materialize_from_vec(vec!["a".into(), "b".into(), "c".into()]);
+        assert_eq!(*rc, ["a", "b", "c"], "elements are not what was provided");
+        assert_eq!(weak.upgrade().map(|e| e.len()), Some(3), "must be upgradable");
+    }
+
+    #[test]
+    #[should_panic(expected = "vector length 2 doesn't match the slot length 3")]
+    fn test_materialize_from_vec_mismatch() {
+        MaybeRc::<[usize; 3]>::new().materialize_from_vec(vec![1, 2]);
+    }
+
+    #[test]
+    fn test_materialize_from_vec_drops() {
+        use std::cell::Cell;
+
+        struct Flag<'a>(&'a Cell<usize>);
+
+        impl Drop for Flag<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let rc = MaybeRc::<[Flag; 4]>::new().materialize_from_vec((0..4).map(|_| Flag(&drops)).collect());
+        assert_eq!(drops.get(), 0, "moved elements must not be dropped");
+
+        drop(rc);
+        assert_eq!(drops.get(), 4, "every element must be dropped exactly once");
+    }
+
+    #[test]
+    fn test_materialize_from_fn_matches_generic() {
+        let maybe = MaybeRc::<[u64; 64]>::new();
+        let weak = maybe.downgrade();
+
+        let rc = maybe.materialize_from_fn(|index| index as u64 * 3);
+        let generic = MaybeRc::new().materialize(std::array::from_fn::<u64, 64, _>(|index| index as u64 * 3));
+        assert_eq!(*rc, *generic, "must match the generic path");
+        assert!(weak.upgrade().is_some_and(|e| Rc::ptr_eq(&e, &rc)), "must be upgradable");
+
+        let rc = MaybeRc::<[String; 3]>::new().materialize_from_fn(|index| index.to_string());
+        assert_eq!(*rc, ["0", "1", "2"], "must match the generic path");
+    }
+
+    #[test]
+    fn test_materialize_from_fn_panics() {
+        use std::cell::Cell;
+        use std::panic::{self, AssertUnwindSafe};
+
+        struct Flag<'a>(&'a Cell<usize>);
+
+        impl Drop for Flag<'_> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        let maybe = MaybeRc::<[Flag; 4]>::new();
+        let weak = maybe.downgrade();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            maybe.materialize_from_fn(|index| match index {
+                2 => panic!("element failed"),
+                _ => Flag(&drops),
+            })
+        }));
+
+        assert!(result.is_err(), "must panic");
+        assert_eq!(drops.get(), 2, "written elements must be dropped exactly once");
+        assert!(weak.upgrade().is_none(), "must not be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_from_slice_matches_generic() {
+        let values: Vec<f64> = (0..128).map(|index| index as f64 / 2.0).collect();
+        let rc = MaybeRc::<[f64; 128]>::new().materialize_from_slice(&values);
+        let generic = MaybeRc::new().materialize(<[f64; 128]>::try_from(&values[..]).unwrap());
+        assert_eq!(*rc, *generic, "must match the generic path");
+    }
+
+    #[test]
+    #[should_panic(expected = "slice length 2 doesn't match the slot length 3")]
+    fn test_materialize_from_slice_mismatch() {
+        MaybeRc::<[usize; 3]>::new().materialize_from_slice(&[1, 2]);
+    }
+
+    #[test]
+    fn test_materialize_from_stream() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        struct Deps {
+            next: usize,
+            ready: bool,
+            weak: Weak<Node>,
+        }
+
+        impl AsyncIterator for Deps {
+            type Item = usize;
+
+            fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<usize>> {
+                assert!(self.weak.upgrade().is_none(), "must not be upgradable while collecting");
+
+                // dependencies arrive with pending polls in between
+                self.ready = !self.ready;
+                if !self.ready {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+
+                self.next += 1;
+                Poll::Ready((self.next <= 3).then_some(self.next))
+            }
+        }
+
+        struct Node {
+            deps: Vec<usize>,
+            me: Weak<Node>,
+        }
+
+        let maybe = MaybeRc::new();
+        let deps = Deps { next: 0, ready: false, weak: maybe.downgrade() };
+        let future = maybe.materialize_from_stream(|me, deps| Node { deps, me: me.clone() }, deps);
+
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut polls = 0;
+        let rc = loop {
+            polls += 1;
+            if let Poll::Ready(rc) = future.as_mut().poll(&mut cx) {
+                break rc;
+            }
+        };
+
+        assert_eq!(rc.deps, [1, 2, 3], "all dependencies must be collected");
+        assert!(polls > 3, "future must be pending between dependencies");
+        assert!(rc.me.upgrade().is_some_and(|me| Rc::ptr_eq(&me, &rc)), "self weak points to a different object");
+    }
+
+    #[test]
+    fn test_materialize_reserved() {
+        let counter = Rc::new(());
+
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+        let rc = maybe.materialize_reserved(counter.clone(), 2);
+        assert_eq!(Rc::strong_count(&rc), 3, "strong count must include the reservation");
+
+        let ptr = Rc::as_ptr(&rc);
+        drop(rc);
+        assert!(weak.upgrade().is_some(), "reserved references must keep the value alive");
+
+        // SAFETY: two references were reserved
+        let reserved = unsafe { [Rc::from_raw(ptr), Rc::from_raw(ptr)] };
+        assert_eq!(Rc::strong_count(&reserved[0]), 2, "strong count must match reconstructed references");
+
+        drop(reserved);
+        assert!(weak.upgrade().is_none(), "value must be dropped once reservation is balanced");
+        assert_eq!(Rc::strong_count(&counter), 1, "value must be dropped exactly once");
+    }
+
+    #[test]
+    fn test_materialize_leaf() {
+        let rc = MaybeRc::<usize>::new().materialize_leaf(42);
+        assert_eq!(*rc, 42, "value is not what was provided");
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "leaf node must not have weak references")]
+    fn test_materialize_leaf_with_weak() {
+        let maybe = MaybeRc::<usize>::new();
+        let _weak = maybe.downgrade();
+        maybe.materialize_leaf(42);
+    }
+
+    struct SelfRef {
+        me: Weak<SelfRef>,
+    }
+
+    #[test]
+    fn test_materialize_verify_self() {
+        let maybe = MaybeRc::new();
+        let me = maybe.downgrade();
+        let rc = maybe.materialize_verify_self(SelfRef { me }, |node| &node.me);
+        assert!(rc.me.upgrade().is_some_and(|me| Rc::ptr_eq(&me, &rc)), "self weak must be upgradable");
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "self weak must point to the materialized value")]
+    fn test_materialize_verify_self_wrong() {
+        let other = MaybeRc::<SelfRef>::new();
+        let maybe = MaybeRc::new();
+        maybe.materialize_verify_self(SelfRef { me: other.downgrade() }, |node| &node.me);
+    }
+
+    #[test]
+    fn test_materialize_unique() {
+        let maybe = MaybeRc::<usize>::new();
+        let weak = maybe.downgrade();
+
+        let mut unique = maybe.materialize_unique(1);
+        *unique += 41;
+        assert!(weak.upgrade().is_none(), "must not be upgradable while unique");
+
+        let rc = UniqueMaybeRc::share(unique);
+        assert_eq!(*rc, 42, "value is not what was set");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_unique_drop() {
+        let counter = Rc::new(());
+
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+        let unique = maybe.materialize_unique(counter.clone());
+        drop(unique);
+
+        assert_eq!(Rc::strong_count(&counter), 1, "value must be dropped");
+        assert!(weak.upgrade().is_none(), "must not be upgradable");
+    }
+
+    #[test]
+    fn test_materialize_self_ptr() {
+        struct Node {
+            value: usize,
+            me: *const Node,
+        }
+
+        let rc = MaybeRc::new().materialize_self_ptr(|me| Node { value: 42, me });
+        assert_eq!(rc.me, Rc::as_ptr(&rc), "self pointer must point to the materialized value");
+
+        // SAFETY: allocation is alive and the value was never moved
+        assert_eq!(unsafe { (*rc.me).value }, 42, "value must be readable through the self pointer");
+    }
+
+    #[test]
+    fn test_materialize_pinned_unique() {
+        use std::marker::PhantomPinned;
+
+        struct Intrusive {
+            value: usize,
+            me: *const Intrusive,
+            _pin: PhantomPinned,
+        }
+
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+
+        let node = Intrusive { value: 42, me: std::ptr::null(), _pin: PhantomPinned };
+        // SAFETY: value is never moved out of the upgraded weak
+        let mut unique = unsafe { maybe.materialize_pinned_unique(node) };
+
+        // SAFETY: only a field is written, value itself is not moved
+        unsafe {
+            let node = unique.as_mut().get_unchecked_mut();
+            node.me = node;
+        }
+
+        let rc = UniqueMaybeRc::share_pinned(unique);
+        assert_eq!(rc.me, &*rc as *const Intrusive, "self pointer must point to the shared value");
+
+        // SAFETY: pointer targets the pinned allocation which is still alive
+        assert_eq!(unsafe { (*rc.me).value }, 42, "value must be readable through the self pointer");
+        assert_eq!(weak.as_ptr(), rc.me, "Weak and self pointer point to a different objects");
+    }
+
+    #[test]
+    fn test_weak_from_ref() {
+        fn take_weak(weak: impl Into<Weak<usize>>) -> Weak<usize> {
+            weak.into()
+        }
+
+        let maybe = MaybeRc::new();
+        let weak = take_weak(&maybe);
+        assert_eq!(weak.as_ptr(), maybe.as_ptr(), "Weak points to a different object");
+
+        let rc = maybe.materialize(42);
+        assert!(Weak::ptr_eq(&weak, &Rc::downgrade(&rc)), "Weak points to a different object");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+    }
 }
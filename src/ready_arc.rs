@@ -0,0 +1,187 @@
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+
+use crate::MaybeArc;
+
+struct ReadyNode<T> {
+    ready: AtomicBool,
+    value: T,
+}
+
+/// A `MaybeArc<T>` which publishes a readiness flag on materialization
+///
+/// Its `ReadyWeak<T>` references can check if the value was materialized with a single
+/// atomic load and without touching the reference counts of the value.
+///
+/// The flag is stored in the node's allocation next to the value and is initialized before
+/// the value itself. Every node costs one extra word for word-aligned values (the flag
+/// padded to the alignment of `T`) and no extra allocation.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::ReadyMaybeArc;
+///
+/// let maybe = ReadyMaybeArc::new();
+/// let weak = maybe.downgrade();
+/// assert!(!weak.poll_ready());
+///
+/// let arc = maybe.materialize(42);
+/// assert!(weak.poll_ready());
+/// assert_eq!(weak.upgrade().map(|e| *e), Some(42));
+/// ```
+pub struct ReadyMaybeArc<T> {
+    maybe: MaybeArc<ReadyNode<T>>,
+    // taken before any `Weak<T>` exists, values are written through it without borrowing the node
+    node: *mut ReadyNode<T>,
+}
+
+// SAFETY: the pointer only refers to the owned allocation, same bounds as `MaybeArc<T>`
+unsafe impl<T: Send + Sync> Send for ReadyMaybeArc<T> {}
+unsafe impl<T: Send + Sync> Sync for ReadyMaybeArc<T> {}
+
+/// Shared reference created by `ReadyMaybeArc::materialize`
+pub struct ReadyArc<T> {
+    arc: Arc<ReadyNode<T>>,
+}
+
+/// A `Weak<T>` reference created by `ReadyMaybeArc<T>`
+pub struct ReadyWeak<T> {
+    weak: Weak<ReadyNode<T>>,
+}
+
+impl<T> ReadyMaybeArc<T> {
+    /// Constructs a new `ReadyMaybeArc<T>`.
+    pub fn new() -> Self {
+        let mut maybe = MaybeArc::<ReadyNode<T>>::new();
+        let node = maybe.as_mut_ptr();
+
+        // SAFETY: the allocation is unique and no `Weak<T>` exists yet
+        unsafe { ptr::addr_of_mut!((*node).ready).write(AtomicBool::new(false)) };
+
+        Self { maybe, node }
+    }
+
+    /// Creates a new `ReadyWeak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> ReadyWeak<T> {
+        ReadyWeak { weak: self.maybe.downgrade() }
+    }
+
+    /// Materialize this allocation to a fully-contructed `ReadyArc<T>` and publish readiness.
+    pub fn materialize(self, value: T) -> ReadyArc<T> {
+        // SAFETY: only the value is written, `ready` may be read concurrently by `ReadyWeak<T>`
+        let arc = unsafe {
+            ptr::addr_of_mut!((*self.node).value).write(value);
+            self.maybe.assume_init()
+        };
+
+        // pairs with `Acquire` in `ReadyWeak::poll_ready` so value is visible once flag is set
+        arc.ready.store(true, Ordering::Release);
+        ReadyArc { arc }
+    }
+}
+
+impl<T> Default for ReadyMaybeArc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ReadyArc<T> {
+    /// Returns `true` if both references point to the same node.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.arc, &other.arc)
+    }
+}
+
+impl<T> Deref for ReadyArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.arc.value
+    }
+}
+
+impl<T> Clone for ReadyArc<T> {
+    fn clone(&self) -> Self {
+        Self { arc: self.arc.clone() }
+    }
+}
+
+impl<T> ReadyWeak<T> {
+    /// Returns `true` if the value was materialized.
+    ///
+    /// Stays `true` after the value is dropped, `upgrade` will fail in that case.
+    pub fn poll_ready(&self) -> bool {
+        // SAFETY: the allocation is kept by this reference, `ready` is initialized before
+        // any `Weak<T>` exists, is only accessed atomically and has no drop glue
+        let ready = unsafe { &*ptr::addr_of!((*self.weak.as_ptr()).ready) };
+        ready.load(Ordering::Acquire)
+    }
+
+    /// Attempts to upgrade to a `ReadyArc<T>`, same as `Weak<T>::upgrade`.
+    pub fn upgrade(&self) -> Option<ReadyArc<T>> {
+        self.weak.upgrade().map(|arc| ReadyArc { arc })
+    }
+}
+
+impl<T> Clone for ReadyWeak<T> {
+    fn clone(&self) -> Self {
+        Self { weak: self.weak.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::mem;
+    use std::thread;
+
+    #[test]
+    fn test_poll_ready() {
+        let maybe = ReadyMaybeArc::<usize>::new();
+
+        let weak = maybe.downgrade();
+        assert!(!weak.poll_ready(), "must not be ready");
+
+        let arc = maybe.materialize(42);
+        assert!(weak.poll_ready(), "must be ready");
+        assert_eq!(weak.upgrade().map(|e| *e), Some(42), "must be upgradable");
+
+        drop(arc);
+        assert!(weak.poll_ready(), "must stay ready");
+        assert!(weak.upgrade().is_none(), "must not be upgradable");
+    }
+
+    #[test]
+    fn test_poll_ready_ordering() {
+        let maybe = ReadyMaybeArc::<Vec<usize>>::new();
+        let weak = maybe.downgrade();
+
+        let poller = thread::spawn(move || {
+            while !weak.poll_ready() {
+                thread::yield_now();
+            }
+            weak.upgrade().map(|e| e.iter().sum::<usize>())
+        });
+
+        let arc = maybe.materialize(vec![1, 2, 3]);
+        assert_eq!(poller.join().unwrap(), Some(6), "value must be visible once ready");
+        drop(arc);
+    }
+
+    #[test]
+    fn test_single_allocation() {
+        let maybe = ReadyMaybeArc::<u64>::new();
+        let weak = maybe.downgrade();
+        assert_eq!(mem::size_of::<ReadyWeak<u64>>(), mem::size_of::<Weak<u64>>(), "weak must not carry the flag");
+
+        let arc = maybe.materialize(42);
+        let upgraded = weak.upgrade().expect("must be upgradable");
+        assert!(ReadyArc::ptr_eq(&upgraded, &arc), "must upgrade to the same node");
+        assert_eq!(mem::size_of::<ReadyNode<u64>>(), 2 * mem::size_of::<u64>(), "flag must cost one word");
+    }
+}
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+const BITS: usize = u64::BITS as usize;
+
+/// A group of `MaybeRc<T>` nodes with their materialization flags packed into a bitset
+///
+/// Checking readiness through `Weak<T>::strong_count` touches every node allocation,
+/// the group keeps one bit per node in a separate dense allocation instead, so
+/// scheduler-style consumers can scan many nodes at once.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::ReadyGroup;
+///
+/// let group = ReadyGroup::new();
+/// let first = group.add::<usize>();
+/// let second = group.add::<usize>();
+///
+/// let rc = second.materialize(42);
+/// assert_eq!(group.ready(), [1]);
+/// assert!(!group.is_ready(first.index()));
+/// ```
+#[derive(Clone, Default)]
+pub struct ReadyGroup {
+    inner: Rc<RefCell<Bits>>,
+}
+
+#[derive(Default)]
+struct Bits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+/// A `MaybeRc<T>` which is a member of a `ReadyGroup`
+pub struct GroupMaybeRc<T> {
+    maybe: MaybeRc<T>,
+    group: ReadyGroup,
+    index: usize,
+}
+
+impl ReadyGroup {
+    /// Constructs a new empty `ReadyGroup`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new node to this group.
+    pub fn add<T>(&self) -> GroupMaybeRc<T> {
+        let mut bits = self.inner.borrow_mut();
+        let index = bits.len;
+        bits.len += 1;
+        if index.is_multiple_of(BITS) {
+            bits.words.push(0);
+        }
+
+        GroupMaybeRc { maybe: MaybeRc::new(), group: self.clone(), index }
+    }
+
+    /// Returns `true` if the node at `index` was materialized.
+    ///
+    /// Stays `true` after the node is dropped.
+    pub fn is_ready(&self, index: usize) -> bool {
+        let bits = self.inner.borrow();
+        bits.words.get(index / BITS).is_some_and(|word| word & (1 << (index % BITS)) != 0)
+    }
+
+    /// Returns the number of materialized nodes.
+    pub fn count_ready(&self) -> usize {
+        self.inner.borrow().words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns indices of all materialized nodes in ascending order.
+    pub fn ready(&self) -> Vec<usize> {
+        let bits = self.inner.borrow();
+        let mut ready = Vec::new();
+        for (word_index, &word) in bits.words.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                ready.push(word_index * BITS + word.trailing_zeros() as usize);
+                word &= word - 1;
+            }
+        }
+        ready
+    }
+
+    /// Returns the number of nodes added to this group.
+    pub fn len(&self) -> usize {
+        self.inner.borrow().len
+    }
+
+    /// Returns `true` if no nodes were added to this group.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> GroupMaybeRc<T> {
+    /// Returns the index of this node's bit in the group.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Creates a new `Weak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> Weak<T> {
+        self.maybe.downgrade()
+    }
+
+    /// Materialize this allocation to a fully-contructed `Rc<T>` and set its bit in the group.
+    pub fn materialize(self, value: T) -> Rc<T> {
+        let rc = self.maybe.materialize(value);
+        self.group.inner.borrow_mut().words[self.index / BITS] |= 1 << (self.index % BITS);
+        rc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subset_ready() {
+        let group = ReadyGroup::new();
+        let nodes: Vec<_> = (0..200).map(|_| group.add::<usize>()).collect();
+        assert_eq!(group.len(), 200, "all nodes must be added");
+
+        let expected: Vec<usize> = (0..200).step_by(3).chain([199]).collect();
+        let mut weaks = Vec::new();
+        let mut rcs = Vec::new();
+        for node in nodes {
+            let index = node.index();
+            weaks.push(node.downgrade());
+            if expected.contains(&index) {
+                rcs.push(node.materialize(index));
+            }
+        }
+
+        assert_eq!(group.ready(), expected, "bitset doesn't match materialized nodes");
+        assert_eq!(group.count_ready(), expected.len(), "incorrect number of ready nodes");
+        for (index, weak) in weaks.iter().enumerate() {
+            assert_eq!(group.is_ready(index), weak.upgrade().is_some(), "bit {} doesn't match the node", index);
+        }
+        assert!(!group.is_ready(200), "out of bounds index must not be ready");
+    }
+}
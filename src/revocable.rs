@@ -0,0 +1,145 @@
+use std::cell::Cell;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// A `MaybeRc<T>` whose weak references can be revoked after materialization
+///
+/// `RevocableWeak<T>` adds a shared revocation flag on top of `Weak<T>`. Once the `RevokeToken<T>`
+/// returned by `materialize_revocable` is triggered, every `RevocableWeak<T>` of this node stops
+/// upgrading and returns `None`, even while strong references to the node are still alive.
+/// Already upgraded `Rc<T>` references are not affected.
+///
+/// Plain `Weak<T>` references can't be revoked, so this node doesn't hand them out.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::RevocableMaybeRc;
+///
+/// let maybe = RevocableMaybeRc::new();
+/// let weak = maybe.downgrade();
+/// let (rc, token) = maybe.materialize_revocable(42);
+/// assert_eq!(weak.upgrade().map(|e| *e), Some(42));
+///
+/// token.revoke();
+/// assert!(weak.upgrade().is_none());
+/// assert_eq!(*rc, 42);
+/// ```
+pub struct RevocableMaybeRc<T> {
+    maybe: MaybeRc<T>,
+    revoked: Rc<Cell<bool>>,
+}
+
+/// Weak reference created by `RevocableMaybeRc<T>` which stops upgrading once revoked
+pub struct RevocableWeak<T> {
+    weak: Weak<T>,
+    revoked: Rc<Cell<bool>>,
+}
+
+/// Revokes all `RevocableWeak<T>` references of a node
+pub struct RevokeToken<T> {
+    weak: Weak<T>,
+    revoked: Rc<Cell<bool>>,
+}
+
+impl<T> RevocableMaybeRc<T> {
+    /// Constructs a new `RevocableMaybeRc<T>`.
+    pub fn new() -> Self {
+        Self { maybe: MaybeRc::new(), revoked: Rc::default() }
+    }
+
+    /// Creates a new `RevocableWeak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> RevocableWeak<T> {
+        RevocableWeak { weak: self.maybe.downgrade(), revoked: self.revoked.clone() }
+    }
+
+    /// Materialize this allocation to a fully-contructed `Rc<T>` and returns a token revoking its weak references.
+    pub fn materialize_revocable(self, value: T) -> (Rc<T>, RevokeToken<T>) {
+        let rc = self.maybe.materialize(value);
+        let token = RevokeToken { weak: Rc::downgrade(&rc), revoked: self.revoked };
+        (rc, token)
+    }
+}
+
+impl<T> Default for RevocableMaybeRc<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> RevocableWeak<T> {
+    /// Attempts to upgrade to an `Rc<T>`.
+    ///
+    /// Returns `None` if the node was revoked, dropped or not materialized yet.
+    pub fn upgrade(&self) -> Option<Rc<T>> {
+        match self.revoked.get() {
+            true => None,
+            false => self.weak.upgrade(),
+        }
+    }
+
+    /// Returns `true` if this reference was revoked.
+    pub fn is_revoked(&self) -> bool {
+        self.revoked.get()
+    }
+}
+
+impl<T> Clone for RevocableWeak<T> {
+    fn clone(&self) -> Self {
+        Self { weak: self.weak.clone(), revoked: self.revoked.clone() }
+    }
+}
+
+impl<T> RevokeToken<T> {
+    /// Revokes all `RevocableWeak<T>` references of this node, including ones created later.
+    pub fn revoke(&self) {
+        self.revoked.set(true);
+    }
+
+    /// Returns `true` if this node was revoked.
+    pub fn is_revoked(&self) -> bool {
+        self.revoked.get()
+    }
+
+    /// Creates a new `RevocableWeak<T>` pointer to the materialized node.
+    pub fn downgrade(&self) -> RevocableWeak<T> {
+        RevocableWeak { weak: self.weak.clone(), revoked: self.revoked.clone() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revoke() {
+        let maybe = RevocableMaybeRc::new();
+        let weak = maybe.downgrade();
+        assert!(weak.upgrade().is_none(), "must not be upgradable before materialization");
+
+        let (rc, token) = maybe.materialize_revocable(42);
+        let upgraded = weak.upgrade().expect("must be upgradable before revocation");
+        assert!(Rc::ptr_eq(&upgraded, &rc), "must upgrade to the materialized node");
+        assert!(token.downgrade().upgrade().is_some(), "weak from token must be upgradable");
+
+        token.revoke();
+        assert!(weak.is_revoked() && token.is_revoked(), "must be revoked");
+        assert!(weak.upgrade().is_none(), "must not be upgradable after revocation");
+        assert!(weak.clone().upgrade().is_none(), "clone must not be upgradable after revocation");
+        assert!(token.downgrade().upgrade().is_none(), "new weak must not be upgradable after revocation");
+        assert_eq!((*rc, *upgraded), (42, 42), "strong references must not be affected");
+    }
+
+    #[test]
+    fn test_revoke_after_drop() {
+        let maybe = RevocableMaybeRc::new();
+        let weak = maybe.downgrade();
+        let (rc, token) = maybe.materialize_revocable(42);
+
+        drop(rc);
+        assert!(weak.upgrade().is_none(), "dropped node must not be upgradable");
+        token.revoke();
+        assert!(weak.upgrade().is_none(), "must not be upgradable after revocation");
+    }
+}
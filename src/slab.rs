@@ -0,0 +1,263 @@
+use std::alloc::{handle_alloc_error, AllocError, Allocator, Global, Layout};
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+use std::rc::Rc;
+
+use crate::MaybeRc;
+
+struct Block {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    slot: Layout,
+    capacity: usize,
+    // slots below `bump` were handed out at least once
+    bump: Cell<usize>,
+    // intrusive list of freed slots, each one keeps a pointer to the next in its first word
+    free: Cell<Option<NonNull<u8>>>,
+    used: Cell<usize>,
+}
+
+impl Block {
+    fn slot_index(&self, ptr: NonNull<u8>) -> Option<usize> {
+        let offset = (ptr.as_ptr() as usize).checked_sub(self.ptr.as_ptr() as usize)?;
+        (offset < self.layout.size()).then(|| offset / self.slot.size())
+    }
+
+    fn pop(&self) -> Option<NonNull<u8>> {
+        let ptr = match self.free.get() {
+            Some(ptr) => {
+                // SAFETY: freed slots keep the next pointer in their first word, see `push`
+                self.free.set(unsafe { ptr.cast::<Option<NonNull<u8>>>().read() });
+                ptr
+            }
+            None if self.bump.get() < self.capacity => {
+                let index = self.bump.get();
+                self.bump.set(index + 1);
+                // SAFETY: index is in bounds of the block
+                unsafe { self.ptr.add(index * self.slot.size()) }
+            }
+            None => return None,
+        };
+
+        self.used.set(self.used.get() + 1);
+        Some(ptr)
+    }
+
+    /// SAFETY: `ptr` must be a slot of this block which is not in use anymore
+    unsafe fn push(&self, ptr: NonNull<u8>) {
+        // SAFETY: slots are aligned for and hold at least the two reference counts
+        unsafe { ptr.cast::<Option<NonNull<u8>>>().write(self.free.get()) };
+        self.free.set(Some(ptr));
+        self.used.set(self.used.get() - 1);
+    }
+}
+
+impl Drop for Block {
+    fn drop(&mut self) {
+        if self.layout.size() > 0 {
+            // SAFETY: block was allocated with the same layout in `Slab::with_capacity`
+            unsafe { Global.deallocate(self.ptr, self.layout) };
+        }
+    }
+}
+
+/// Allocator handing out slots of a single contiguous block owned by a `Slab<T>`
+///
+/// Every `Rc<T, SlabAllocator>` and `Weak<T, SlabAllocator>` holds a clone of it,
+/// so the block is freed only after the last node allocated from it.
+#[derive(Clone)]
+pub struct SlabAllocator {
+    block: Rc<Block>,
+}
+
+unsafe impl Allocator for SlabAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let slot = self.block.slot;
+        if layout.size() <= slot.size() && layout.align() <= slot.align() {
+            if let Some(ptr) = self.block.pop() {
+                return Ok(NonNull::slice_from_raw_parts(ptr, slot.size()));
+            }
+        }
+
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self.block.slot_index(ptr) {
+            // SAFETY: the slot is deallocated, so it is not in use anymore
+            Some(_) => unsafe { self.block.push(ptr) },
+            // SAFETY: pointers outside of the block were allocated by `Global`
+            None => unsafe { Global.deallocate(ptr, layout) },
+        }
+    }
+}
+
+/// Bulk allocator of `MaybeRc<T>` nodes backed by a single contiguous block
+///
+/// Every node still has its own independent reference counts inside its slot, so nodes
+/// can be materialized, upgraded and dropped in any order. A slot is reused once all
+/// `Rc<T>` and `Weak<T>` references to its node are dropped.
+///
+/// Slots are handed out without locking as the slab and its nodes stay on one thread.
+/// When the slab is exhausted nodes are allocated with the global allocator.
+///
+/// # Examples
+///
+/// ```
+/// #![feature(allocator_api)]
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::{Slab, SlabAllocator};
+///
+/// struct Node {
+///     next: Weak<Node, SlabAllocator>,
+/// }
+///
+/// let slab = Slab::with_capacity(2);
+/// let (a, b) = (slab.alloc(), slab.alloc());
+/// let (a_weak, b_weak) = (a.downgrade(), b.downgrade());
+///
+/// let a = a.materialize(Node { next: b_weak });
+/// let b = b.materialize(Node { next: a_weak });
+///
+/// assert_eq!(slab.remaining(), 0);
+/// assert!(a.next.upgrade().is_some_and(|next| Rc::ptr_eq(&next, &b)));
+/// ```
+pub struct Slab<T> {
+    alloc: SlabAllocator,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Slab<T> {
+    /// Constructs a new `Slab<T>` with room for `capacity` nodes allocated upfront.
+    pub fn with_capacity(capacity: usize) -> Self {
+        // matches the allocation made by `MaybeRc::new_in`
+        let (slot, _) = Layout::new::<[usize; 2]>()
+            .extend(Layout::new::<T>())
+            .expect("slot size overflow");
+        let slot = slot.pad_to_align();
+
+        let size = slot.size().checked_mul(capacity).expect("slab size overflow");
+        let layout = Layout::from_size_align(size, slot.align()).expect("slab size overflow");
+        let ptr = match size {
+            0 => NonNull::dangling(),
+            _ => match Global.allocate(layout) {
+                Ok(ptr) => ptr.cast(),
+                Err(_) => handle_alloc_error(layout),
+            },
+        };
+
+        let block = Rc::new(Block {
+            ptr,
+            layout,
+            slot,
+            capacity,
+            bump: Cell::new(0),
+            free: Cell::new(None),
+            used: Cell::new(0),
+        });
+
+        Self { alloc: SlabAllocator { block }, _marker: PhantomData }
+    }
+
+    /// Allocates a new `MaybeRc<T>` in a free slot of this slab.
+    pub fn alloc(&self) -> MaybeRc<T, SlabAllocator> {
+        MaybeRc::new_in(self.alloc.clone())
+    }
+
+    /// Returns the number of free slots.
+    pub fn remaining(&self) -> usize {
+        self.alloc.block.capacity - self.alloc.block.used.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes_in_block() {
+        let slab = Slab::<[u64; 3]>::with_capacity(4);
+        let block = slab.alloc.block.clone();
+
+        let nodes: Vec<_> = (0..4).map(|_| slab.alloc()).collect();
+        assert_eq!(slab.remaining(), 0, "all slots must be used");
+
+        let weaks: Vec<_> = nodes.iter().map(|node| node.downgrade()).collect();
+        let rcs: Vec<_> = nodes.into_iter()
+            .enumerate()
+            .map(|(index, node)| node.materialize([index as u64; 3]))
+            .collect();
+
+        let mut slots: Vec<_> = rcs.iter()
+            .map(|rc| block.slot_index(NonNull::from(&**rc).cast()).expect("node must be in the block"))
+            .collect();
+        slots.sort();
+        assert_eq!(slots, [0, 1, 2, 3], "nodes must use different slots");
+
+        for (index, weak) in weaks.iter().enumerate() {
+            assert_eq!(weak.upgrade().map(|e| *e), Some([index as u64; 3]), "must be upgradable");
+        }
+    }
+
+    #[test]
+    fn test_independent_counts() {
+        let slab = Slab::with_capacity(2);
+        let first = slab.alloc();
+        let second = slab.alloc();
+        let first_weak = first.downgrade();
+        let second_weak = second.downgrade();
+
+        let first = first.materialize(1);
+        let second = second.materialize(2);
+
+        drop(first);
+        assert!(first_weak.upgrade().is_none(), "dropped node must not be upgradable");
+        assert_eq!(slab.remaining(), 0, "slot must be kept while weak exists");
+
+        drop(first_weak);
+        assert_eq!(slab.remaining(), 1, "slot must be freed with the last weak");
+        assert_eq!(second_weak.upgrade().map(|e| *e), Some(2), "other node must not be affected");
+
+        let third = slab.alloc().materialize(3);
+        assert_eq!(slab.remaining(), 0, "freed slot must be reused");
+        assert_eq!((*second, *third), (2, 3), "values are not what was provided");
+    }
+
+    #[test]
+    fn test_free_list_order() {
+        let slab = Slab::with_capacity(3);
+        let nodes: Vec<_> = (0..3).map(|index| slab.alloc().materialize(index)).collect();
+        let ptrs: Vec<_> = nodes.iter().map(Rc::as_ptr).collect();
+
+        drop(nodes);
+        assert_eq!(slab.remaining(), 3, "all slots must be freed");
+
+        let reused: Vec<_> = (0..3).map(|index| slab.alloc().materialize(index)).collect();
+        for rc in &reused {
+            assert!(ptrs.contains(&Rc::as_ptr(rc)), "freed slots must be reused");
+        }
+        assert_ne!(Rc::as_ptr(&reused[0]), Rc::as_ptr(&reused[1]), "slots must not be handed out twice");
+    }
+
+    #[test]
+    fn test_exhausted() {
+        let slab = Slab::with_capacity(1);
+        let first = slab.alloc().materialize(1);
+        let second = slab.alloc().materialize(2);
+
+        assert!(slab.alloc.block.slot_index(NonNull::from(&*first).cast()).is_some(), "must be in the block");
+        assert!(slab.alloc.block.slot_index(NonNull::from(&*second).cast()).is_none(), "must fall back to global");
+        assert_eq!((*first, *second), (1, 2), "values are not what was provided");
+    }
+
+    #[test]
+    fn test_outlives_slab() {
+        let slab = Slab::with_capacity(1);
+        let maybe = slab.alloc();
+        drop(slab);
+
+        let rc = maybe.materialize(String::from("value"));
+        assert_eq!(*rc, "value", "node must outlive the slab");
+    }
+}
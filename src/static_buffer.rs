@@ -0,0 +1,148 @@
+use std::alloc::{AllocError, Allocator, Layout};
+use std::mem::{self, MaybeUninit};
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{MaybeArc, MaybeRc};
+
+/// Allocator placing a single node into a caller-provided `'static` buffer
+///
+/// The start of the buffer keeps an "in use" flag, the rest of it is handed out as one allocation
+/// at a time. Allocations that don't fit or are made while the previous one is still alive fail.
+/// Memory of the buffer is never returned to the heap, once all `Arc<T>` and `Weak<T>` references
+/// are dropped (or `Rc<T>` and its `Weak<T>`) it can only be reused by clones of this allocator.
+#[derive(Clone, Copy)]
+pub struct StaticBufferAllocator {
+    in_use: NonNull<AtomicBool>,
+    data: NonNull<u8>,
+    len: usize,
+}
+
+// SAFETY: data of the buffer is only handed out while `in_use` is set, which is an atomic flag
+unsafe impl Send for StaticBufferAllocator {}
+unsafe impl Sync for StaticBufferAllocator {}
+
+impl StaticBufferAllocator {
+    fn new(buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        let flag = buf.as_mut_ptr().align_offset(mem::align_of::<AtomicBool>());
+        let start = flag + mem::size_of::<AtomicBool>();
+        assert!(start <= buf.len(), "buffer of {} bytes can't hold its header", buf.len());
+
+        // SAFETY: `flag..start` is in bounds and aligned for `AtomicBool`
+        let in_use = unsafe {
+            let ptr = buf.as_mut_ptr().add(flag).cast::<AtomicBool>();
+            ptr.write(AtomicBool::new(false));
+            NonNull::new_unchecked(ptr)
+        };
+
+        // SAFETY: `start` is in bounds of the buffer
+        let data = unsafe { NonNull::new_unchecked(buf.as_mut_ptr().add(start).cast()) };
+
+        Self { in_use, data, len: buf.len() - start }
+    }
+
+    /// Builds the allocator checking that `buf` can hold a node with a value of type `T`
+    fn for_node<T>(buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        let alloc = Self::new(buf);
+
+        // matches the allocation made by `MaybeRc::new_in` and `MaybeArc::new_in`
+        let (layout, _) = Layout::new::<[usize; 2]>()
+            .extend(Layout::new::<T>())
+            .expect("allocation size overflow");
+        assert!(
+            alloc.fits(layout.pad_to_align()).is_some(),
+            "buffer of {} bytes is too small for a node of {} bytes",
+            alloc.len, layout.pad_to_align().size(),
+        );
+
+        alloc
+    }
+
+    fn fits(&self, layout: Layout) -> Option<usize> {
+        let offset = self.data.as_ptr().align_offset(layout.align());
+        (offset.checked_add(layout.size())? <= self.len).then_some(offset)
+    }
+
+    fn in_use(&self) -> &AtomicBool {
+        // SAFETY: the flag lives in the `'static` buffer
+        unsafe { self.in_use.as_ref() }
+    }
+}
+
+unsafe impl Allocator for StaticBufferAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let offset = self.fits(layout).ok_or(AllocError)?;
+        if self.in_use().swap(true, Ordering::Acquire) {
+            return Err(AllocError);
+        }
+
+        // SAFETY: `offset..offset + size` is in bounds of the data
+        let ptr = unsafe { self.data.add(offset) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        self.in_use().store(false, Ordering::Release);
+    }
+}
+
+impl<T> MaybeArc<T, StaticBufferAllocator> {
+    /// Constructs a new `MaybeArc<T>` placed into `buf` instead of the heap.
+    ///
+    /// Reference counts are still managed by std, the node is allocated through
+    /// `StaticBufferAllocator`, so no assumptions about std's `ArcInner` layout are made
+    /// beyond sizing the buffer. `Weak<T>` references behave the same as for heap nodes.
+    ///
+    /// The buffer needs room for a one byte header, the reference counts (two `usize`) and
+    /// `T`, plus padding to align them, see `MaybeArc::allocation_size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is too small to hold the node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use std::mem::MaybeUninit;
+    /// use maybe_rc::MaybeArc;
+    ///
+    /// let buf = Box::leak(Box::new([MaybeUninit::uninit(); 64]));
+    /// let maybe = MaybeArc::<u64, _>::from_uninit_buffer(buf);
+    /// let weak = maybe.downgrade();
+    ///
+    /// let arc = maybe.materialize(42);
+    /// assert_eq!(weak.upgrade().map(|e| *e), Some(42));
+    /// ```
+    pub fn from_uninit_buffer(buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self::new_in(StaticBufferAllocator::for_node::<T>(buf))
+    }
+}
+
+impl<T> MaybeRc<T, StaticBufferAllocator> {
+    /// Constructs a new `MaybeRc<T>` placed into `buf` instead of the heap.
+    ///
+    /// Same as `MaybeArc::from_uninit_buffer` for `Rc<T>` nodes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is too small to hold the node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use std::mem::MaybeUninit;
+    /// use maybe_rc::MaybeRc;
+    ///
+    /// let buf = Box::leak(Box::new([MaybeUninit::uninit(); 64]));
+    /// let maybe = MaybeRc::<u64, _>::from_uninit_buffer(buf);
+    /// let weak = maybe.downgrade();
+    ///
+    /// let rc = maybe.materialize(42);
+    /// assert_eq!(weak.upgrade().map(|e| *e), Some(42));
+    /// ```
+    pub fn from_uninit_buffer(buf: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self::new_in(StaticBufferAllocator::for_node::<T>(buf))
+    }
+}
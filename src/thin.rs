@@ -0,0 +1,339 @@
+use std::marker::{PhantomData, Unsize};
+use std::mem::{self, ManuallyDrop};
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// Reference counting operations of a concrete `Rc<ThinNode<T, U>>` with its type erased
+struct Ops {
+    clone_strong: unsafe fn(*const ()),
+    drop_strong: unsafe fn(*const ()),
+    upgrade: unsafe fn(*const ()) -> bool,
+    clone_weak: unsafe fn(*const ()),
+    drop_weak: unsafe fn(*const ()),
+}
+
+struct OpsFor<N>(PhantomData<N>);
+
+impl<N> OpsFor<N> {
+    const OPS: Ops = Ops {
+        clone_strong: clone_strong::<N>,
+        drop_strong: drop_strong::<N>,
+        upgrade: upgrade::<N>,
+        clone_weak: clone_weak::<N>,
+        drop_weak: drop_weak::<N>,
+    };
+}
+
+/// SAFETY: for all ops `ptr` must come from `Rc::<N>::into_raw` or `Weak::<N>::into_raw`
+/// with the matching count held by the caller
+unsafe fn clone_strong<N>(ptr: *const ()) {
+    unsafe { Rc::increment_strong_count(ptr.cast::<N>()) }
+}
+
+unsafe fn drop_strong<N>(ptr: *const ()) {
+    drop(unsafe { Rc::from_raw(ptr.cast::<N>()) });
+}
+
+// on success the new strong reference is owned by the caller
+unsafe fn upgrade<N>(ptr: *const ()) -> bool {
+    let weak = ManuallyDrop::new(unsafe { Weak::from_raw(ptr.cast::<N>()) });
+    weak.upgrade().map(mem::forget).is_some()
+}
+
+unsafe fn clone_weak<N>(ptr: *const ()) {
+    let weak = ManuallyDrop::new(unsafe { Weak::from_raw(ptr.cast::<N>()) });
+    mem::forget(Weak::clone(&weak));
+}
+
+unsafe fn drop_weak<N>(ptr: *const ()) {
+    drop(unsafe { Weak::from_raw(ptr.cast::<N>()) });
+}
+
+/// Header placed at the start of every `ThinMaybeRc` value
+///
+/// Layout is `#[repr(C)]`: a (possibly fat) pointer to the value carrying its metadata
+/// (vtable or length), followed by a pointer to the reference counting operations of the node.
+#[repr(C)]
+pub struct ThinHeader<T: ?Sized> {
+    value: *const T,
+    ops: &'static Ops,
+}
+
+#[repr(C)]
+struct ThinNode<T: ?Sized, U> {
+    header: ThinHeader<T>,
+    value: U,
+}
+
+/// A `MaybeRc` of a `U` value which materializes into a thin `ThinRc<T>`, e.g. for `T = dyn Trait`
+///
+/// `ThinRc<T>` is a single pointer to a `ThinHeader<T>` at the start of the allocation, so it can
+/// be passed through FFI as `*const c_void` and restored with `ThinRc::from_thin_ptr`.
+/// `ThinWeak<T>` references keep the concrete refcount operations next to the pointer,
+/// so they can be created and upgraded before the header is written.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::{ThinMaybeRc, ThinRc, ThinWeak};
+///
+/// trait Named {
+///     fn name(&self) -> &str;
+/// }
+///
+/// struct Node {
+///     me: ThinWeak<dyn Named>,
+/// }
+///
+/// impl Named for Node {
+///     fn name(&self) -> &str {
+///         "node"
+///     }
+/// }
+///
+/// let maybe = ThinMaybeRc::<dyn Named, Node>::new();
+/// let me = maybe.downgrade();
+/// let rc = maybe.materialize(Node { me });
+///
+/// let ptr = ThinRc::into_thin_ptr(rc);
+/// // SAFETY: `ptr` came from `ThinRc::<dyn Named>::into_thin_ptr`
+/// let rc = unsafe { ThinRc::<dyn Named>::from_thin_ptr(ptr) };
+/// assert_eq!(rc.name(), "node");
+/// ```
+pub struct ThinMaybeRc<T: ?Sized, U> {
+    maybe: MaybeRc<ThinNode<T, U>>,
+}
+
+/// Thin shared reference created by `ThinMaybeRc::materialize`
+pub struct ThinRc<T: ?Sized> {
+    ptr: NonNull<ThinHeader<T>>,
+}
+
+/// Weak reference created by `ThinMaybeRc::downgrade` or `ThinRc::downgrade`
+pub struct ThinWeak<T: ?Sized> {
+    ptr: NonNull<ThinHeader<T>>,
+    ops: &'static Ops,
+}
+
+impl<T: ?Sized, U: Unsize<T>> ThinMaybeRc<T, U> {
+    /// Constructs a new `ThinMaybeRc<T, U>`.
+    pub fn new() -> Self {
+        Self { maybe: MaybeRc::new() }
+    }
+
+    /// Creates a new `ThinWeak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> ThinWeak<T> {
+        let ptr = Weak::into_raw(self.maybe.downgrade());
+        ThinWeak {
+            // SAFETY: pointers produced by `Weak::into_raw` of an allocated node are never null
+            ptr: unsafe { NonNull::new_unchecked(ptr as *mut ThinHeader<T>) },
+            ops: &OpsFor::<ThinNode<T, U>>::OPS,
+        }
+    }
+
+    /// Materialize this allocation writing both the header and the value.
+    ///
+    /// All `ThinWeak<T>` references can be upgraded after this method finishes.
+    pub fn materialize(self, value: U) -> ThinRc<T> {
+        let rc = self.maybe.materialize_self_ptr(|node| ThinNode {
+            header: ThinHeader {
+                // SAFETY: only the address of the field is taken, the node is not dereferenced
+                value: unsafe { ptr::addr_of!((*node).value) },
+                ops: &OpsFor::<ThinNode<T, U>>::OPS,
+            },
+            value,
+        });
+
+        // SAFETY: `header` is the first field of the `#[repr(C)]` node
+        ThinRc { ptr: unsafe { NonNull::new_unchecked(Rc::into_raw(rc) as *mut ThinHeader<T>) } }
+    }
+}
+
+impl<T: ?Sized, U: Unsize<T>> Default for ThinMaybeRc<T, U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ?Sized> ThinRc<T> {
+    fn header(&self) -> &ThinHeader<T> {
+        // SAFETY: header is initialized and kept alive by this strong reference
+        unsafe { self.ptr.as_ref() }
+    }
+
+    /// Returns the thin pointer to the header of this allocation.
+    pub fn as_thin_ptr(this: &Self) -> *const () {
+        this.ptr.as_ptr().cast()
+    }
+
+    /// Consumes this reference returning its thin pointer, see `ThinRc::from_thin_ptr`.
+    pub fn into_thin_ptr(this: Self) -> *const () {
+        ManuallyDrop::new(this).ptr.as_ptr().cast()
+    }
+
+    /// Restores a reference consumed by `ThinRc::into_thin_ptr`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `ThinRc::<T>::into_thin_ptr` with the same `T` and
+    /// must be restored only once.
+    pub unsafe fn from_thin_ptr(ptr: *const ()) -> Self {
+        // SAFETY: guaranteed to be a valid header pointer by the caller
+        Self { ptr: unsafe { NonNull::new_unchecked(ptr as *mut ThinHeader<T>) } }
+    }
+
+    /// Creates a new `ThinWeak<T>` pointer to this allocation.
+    pub fn downgrade(this: &Self) -> ThinWeak<T> {
+        let ops = this.header().ops;
+        // SAFETY: this strong reference keeps the allocation alive
+        unsafe { (ops.clone_weak)(this.ptr.as_ptr().cast()) };
+        ThinWeak { ptr: this.ptr, ops }
+    }
+
+    /// Returns `true` if both references point to the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.ptr == other.ptr
+    }
+}
+
+impl<T: ?Sized> Deref for ThinRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: value pointer was written by `materialize` and the value lives as long as this reference
+        unsafe { &*self.header().value }
+    }
+}
+
+impl<T: ?Sized> Clone for ThinRc<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: this strong reference keeps the allocation alive
+        unsafe { (self.header().ops.clone_strong)(self.ptr.as_ptr().cast()) };
+        Self { ptr: self.ptr }
+    }
+}
+
+impl<T: ?Sized> Drop for ThinRc<T> {
+    fn drop(&mut self) {
+        let ops = self.header().ops;
+        // SAFETY: this strong reference is released exactly once
+        unsafe { (ops.drop_strong)(self.ptr.as_ptr().cast()) };
+    }
+}
+
+impl<T: ?Sized> ThinWeak<T> {
+    /// Attempts to upgrade to a `ThinRc<T>`.
+    ///
+    /// Returns `None` before materialization and after the node was dropped.
+    pub fn upgrade(&self) -> Option<ThinRc<T>> {
+        // SAFETY: this weak reference keeps the allocation alive
+        match unsafe { (self.ops.upgrade)(self.ptr.as_ptr().cast()) } {
+            true => Some(ThinRc { ptr: self.ptr }),
+            false => None,
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for ThinWeak<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: this weak reference keeps the allocation alive
+        unsafe { (self.ops.clone_weak)(self.ptr.as_ptr().cast()) };
+        Self { ptr: self.ptr, ops: self.ops }
+    }
+}
+
+impl<T: ?Sized> Drop for ThinWeak<T> {
+    fn drop(&mut self) {
+        // SAFETY: this weak reference is released exactly once
+        unsafe { (self.ops.drop_weak)(self.ptr.as_ptr().cast()) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::ffi::c_void;
+
+    trait Shape {
+        fn area(&self) -> usize;
+        fn parent_area(&self) -> Option<usize>;
+    }
+
+    struct Square {
+        side: usize,
+        parent: ThinWeak<dyn Shape>,
+    }
+
+    impl Shape for Square {
+        fn area(&self) -> usize {
+            self.side * self.side
+        }
+
+        fn parent_area(&self) -> Option<usize> {
+            self.parent.upgrade().map(|parent| parent.area())
+        }
+    }
+
+    struct Group {
+        children: Vec<ThinRc<dyn Shape>>,
+        dropped: Rc<Cell<bool>>,
+    }
+
+    impl Shape for Group {
+        fn area(&self) -> usize {
+            self.children.iter().map(|child| child.area()).sum()
+        }
+
+        fn parent_area(&self) -> Option<usize> {
+            None
+        }
+    }
+
+    impl Drop for Group {
+        fn drop(&mut self) {
+            self.dropped.set(true);
+        }
+    }
+
+    extern "C" fn ffi_area(node: *const c_void) -> usize {
+        // SAFETY: called with a pointer borrowed from a live `ThinRc<dyn Shape>`
+        let node = ManuallyDrop::new(unsafe { ThinRc::<dyn Shape>::from_thin_ptr(node.cast()) });
+        node.area()
+    }
+
+    #[test]
+    fn test_thin_round_trip() {
+        assert_eq!(mem::size_of::<ThinRc<dyn Shape>>(), mem::size_of::<usize>(), "must be a thin pointer");
+
+        let dropped = Rc::new(Cell::new(false));
+        let maybe = ThinMaybeRc::<dyn Shape, Group>::new();
+        let weak = maybe.downgrade();
+        assert!(weak.upgrade().is_none(), "must not be upgradable before materialization");
+
+        let children: Vec<_> = (1..=2)
+            .map(|side| ThinMaybeRc::<dyn Shape, Square>::new().materialize(Square { side, parent: weak.clone() }))
+            .collect();
+        let child = children[1].clone();
+        assert_eq!(child.parent_area(), None, "parent must not be upgradable before materialization");
+
+        let group = maybe.materialize(Group { children, dropped: dropped.clone() });
+        assert_eq!(child.parent_area(), Some(5), "back-edge must upgrade to the parent");
+        assert_eq!(ffi_area(ThinRc::as_thin_ptr(&group).cast()), 5, "method must be callable through FFI");
+
+        let ptr = ThinRc::into_thin_ptr(group.clone());
+        // SAFETY: `ptr` came from `ThinRc::<dyn Shape>::into_thin_ptr`
+        let restored = unsafe { ThinRc::<dyn Shape>::from_thin_ptr(ptr) };
+        assert!(ThinRc::ptr_eq(&restored, &group), "must restore the same node");
+        assert!(ThinRc::ptr_eq(&ThinRc::downgrade(&group).upgrade().unwrap(), &group), "must upgrade to the same node");
+
+        drop((group, restored));
+        assert!(dropped.get(), "node must be dropped with the last strong reference");
+        assert!(weak.upgrade().is_none(), "must not be upgradable after drop");
+        assert_eq!((child.area(), child.parent_area()), (4, None), "child must outlive the parent");
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::{Arc, Weak};
+
+use crate::MaybeArc;
+
+/// A publish/subscribe topic where subscribers register before the value exists
+///
+/// Subscribers get a `Weak<T>` from `Topic::subscribe` which can't be upgraded until
+/// the publisher calls `Topic::publish`, then all of them go live at once.
+/// Unsubscribing is dropping the `Weak<T>`, the value is dropped with the publisher's `Arc<T>`.
+///
+/// # Examples
+///
+/// ```
+/// use maybe_rc::Topic;
+///
+/// let topic = Topic::new();
+/// let subscriber = topic.subscribe();
+/// assert!(subscriber.upgrade().is_none());
+///
+/// let state = topic.publish("ready");
+/// assert_eq!(subscriber.upgrade().map(|e| *e), Some("ready"));
+/// ```
+pub struct Topic<T> {
+    maybe: MaybeArc<T>,
+}
+
+impl<T> Topic<T> {
+    /// Constructs a new `Topic<T>` without a published value.
+    pub fn new() -> Self {
+        Self { maybe: MaybeArc::new() }
+    }
+
+    /// Registers a new subscriber.
+    pub fn subscribe(&self) -> Weak<T> {
+        self.maybe.downgrade()
+    }
+
+    /// Publishes the value making it available to all subscribers.
+    pub fn publish(self, value: T) -> Arc<T> {
+        self.maybe.materialize(value)
+    }
+}
+
+impl<T> Default for Topic<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Barrier;
+    use std::thread;
+
+    #[test]
+    fn test_publish() {
+        const SUBSCRIBERS: usize = 4;
+
+        let topic = Topic::new();
+        let checked = Barrier::new(SUBSCRIBERS + 1);
+        let published = Barrier::new(SUBSCRIBERS + 1);
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..SUBSCRIBERS)
+                .map(|_| {
+                    let subscriber = topic.subscribe();
+                    let (checked, published) = (&checked, &published);
+                    scope.spawn(move || {
+                        let before = subscriber.upgrade().map(|e| *e);
+                        checked.wait();
+                        published.wait();
+                        (before, subscriber.upgrade().map(|e| *e))
+                    })
+                })
+                .collect();
+
+            let unsubscribed = topic.subscribe();
+            drop(unsubscribed);
+
+            checked.wait();
+            let arc = topic.publish(42);
+            published.wait();
+
+            for handle in handles {
+                let (before, after) = handle.join().expect("subscriber must not panic");
+                assert_eq!(before, None, "must not be upgradable before publish");
+                assert_eq!(after, Some(42), "must be upgradable after publish");
+            }
+            assert_eq!(Arc::weak_count(&arc), 0, "subscribers must be dropped with their threads");
+        });
+    }
+
+    #[test]
+    fn test_unsubscribe() {
+        let topic = Topic::new();
+        let first = topic.subscribe();
+        let second = topic.subscribe();
+        drop(first);
+
+        let arc = topic.publish(42);
+        assert_eq!(Arc::weak_count(&arc), 1, "unsubscribed weak must be released");
+
+        drop(arc);
+        assert!(second.upgrade().is_none(), "must not be upgradable after the value is dropped");
+    }
+}
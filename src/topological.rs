@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+use crate::MaybeRc;
+
+/// Dependencies of a node passed to the `build_topological` callback
+pub struct Deps<'a, T> {
+    /// Index of the node being built
+    pub index: usize,
+    /// Already built strong dependencies in the order of declaration
+    pub children: Vec<Rc<T>>,
+    weaks: &'a [Weak<T>],
+}
+
+impl<T> Deps<'_, T> {
+    /// Returns a weak reference to any node, including the ones not built yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn weak(&self, index: usize) -> Weak<T> {
+        self.weaks[index].clone()
+    }
+}
+
+/// Error returned by `build_topological` when strong dependencies form a cycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    node: usize,
+}
+
+impl CycleError {
+    /// Returns index of a node that is part of (or depends on) a cycle.
+    pub fn node(&self) -> usize {
+        self.node
+    }
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node {} is part of a strong dependency cycle", self.node)
+    }
+}
+
+impl Error for CycleError {}
+
+/// Builds a graph materializing nodes in the order of their strong dependencies
+///
+/// `deps[i]` lists the nodes that must be built before node `i`, they are passed to `make`
+/// as `Deps::children`. All nodes are allocated upfront with `MaybeRc`, so `Deps::weak` can
+/// be used for back-edges to any node. Returned nodes are in the order of `deps`.
+///
+/// `make` is not called at all if strong dependencies have a cycle.
+///
+/// # Panics
+///
+/// Panics if any dependency is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::build_topological;
+///
+/// struct Node {
+///     parent: Weak<Node>,
+///     children: Vec<Rc<Node>>,
+/// }
+///
+/// // 0 owns 1 and 2, both of them point back to 0
+/// let nodes = build_topological(&[vec![1, 2], vec![], vec![]], |deps| Node {
+///     parent: deps.weak(0),
+///     children: deps.children,
+/// }).unwrap();
+///
+/// let parent = nodes[1].parent.upgrade().unwrap();
+/// assert!(Rc::ptr_eq(&parent, &nodes[0]));
+/// ```
+pub fn build_topological<T, F>(deps: &[Vec<usize>], mut make: F) -> Result<Vec<Rc<T>>, CycleError>
+    where
+        F: FnMut(Deps<'_, T>) -> T,
+{
+    let mut pending = vec![0usize; deps.len()];
+    let mut dependents = vec![Vec::new(); deps.len()];
+    for (index, node_deps) in deps.iter().enumerate() {
+        for &dep in node_deps {
+            assert!(dep < deps.len(), "dependency {} is out of bounds", dep);
+            pending[index] += 1;
+            dependents[dep].push(index);
+        }
+    }
+
+    let mut queue: VecDeque<_> = (0..deps.len()).filter(|&index| pending[index] == 0).collect();
+    let mut order = Vec::with_capacity(deps.len());
+    while let Some(index) = queue.pop_front() {
+        order.push(index);
+        for &dependent in &dependents[index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                queue.push_back(dependent);
+            }
+        }
+    }
+
+    if let Some(node) = pending.iter().position(|&count| count > 0) {
+        return Err(CycleError { node });
+    }
+
+    let mut maybes: Vec<_> = deps.iter().map(|_| Some(MaybeRc::new())).collect();
+    let weaks: Vec<_> = maybes.iter()
+        .map(|maybe| maybe.as_ref().unwrap().downgrade())
+        .collect();
+
+    let mut built: Vec<Option<Rc<T>>> = vec![None; deps.len()];
+    for index in order {
+        let children = deps[index].iter()
+            .map(|&dep| built[dep].clone().expect("dependency must be built"))
+            .collect();
+
+        let value = make(Deps { index, children, weaks: &weaks });
+        built[index] = Some(maybes[index].take().unwrap().materialize(value));
+    }
+
+    Ok(built.into_iter().map(|rc| rc.unwrap()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Node {
+        index: usize,
+        sum: usize,
+        children: Vec<Rc<Node>>,
+        back: Vec<Weak<Node>>,
+    }
+
+    #[test]
+    fn test_dag() {
+        // 0 -> 1 -> 3, 0 -> 2 -> 3, every node points back to 0
+        let deps = [vec![1, 2], vec![3], vec![3], vec![]];
+        let mut calls = Vec::new();
+
+        let nodes = build_topological(&deps, |deps: Deps<'_, Node>| {
+            calls.push(deps.index);
+            let sum = deps.index + deps.children.iter().map(|child| child.sum).sum::<usize>();
+            Node { index: deps.index, sum, back: vec![deps.weak(0)], children: deps.children }
+        }).expect("graph has no strong cycles");
+
+        assert_eq!(calls.first(), Some(&3), "leaf must be built first");
+        assert_eq!(calls.last(), Some(&0), "root must be built last");
+
+        let indices: Vec<_> = nodes.iter().map(|node| node.index).collect();
+        assert_eq!(indices, [0, 1, 2, 3], "nodes must be returned in the order of declaration");
+        assert_eq!(nodes[0].sum, (1 + 3) + (2 + 3), "children must be built before parents");
+        assert!(Rc::ptr_eq(&nodes[1].children[0], &nodes[2].children[0]), "shared dependency must be built once");
+
+        for node in &nodes {
+            let back = node.back[0].upgrade().expect("back-edge must be upgradable");
+            assert!(Rc::ptr_eq(&back, &nodes[0]), "back-edge points to a different object");
+        }
+    }
+
+    #[test]
+    fn test_strong_cycle() {
+        // 0 -> 1 -> 2 -> 1
+        let deps = [vec![1], vec![2], vec![1]];
+        let result = build_topological(&deps, |_| -> Node { panic!("must not be called") });
+
+        let Err(error) = result else { panic!("cycle must be detected") };
+        assert!(matches!(error.node(), 0..=2), "incorrect cycle node");
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_dep_out_of_bounds() {
+        let _ = build_topological(&[vec![1]], |_| ());
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+use std::rc::{Rc, Weak};
+
+/// Outgoing edge of a traced node
+pub enum Edge<'a, T> {
+    Strong(&'a Rc<T>),
+    Weak(&'a Weak<T>),
+}
+
+/// A graph node that can report its edges to a tracer
+pub trait Trace: Sized {
+    /// Reports all outgoing edges of this node.
+    fn trace(&self, edges: &mut dyn FnMut(Edge<'_, Self>));
+}
+
+/// Traces all nodes reachable from `roots` and reports each of them once to `visit`
+///
+/// `visit` receives a pointer to the node value and all of its weak edges. Weak edges are followed
+/// only if they can be upgraded, so nodes that are not materialized yet are reported as edges only.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::{trace, Edge, Trace};
+///
+/// struct Node(Weak<Node>);
+///
+/// impl Trace for Node {
+///     fn trace(&self, edges: &mut dyn FnMut(Edge<'_, Self>)) {
+///         edges(Edge::Weak(&self.0));
+///     }
+/// }
+///
+/// let node = Rc::new_cyclic(|weak| Node(weak.clone()));
+///
+/// let mut visited = Vec::new();
+/// trace(std::slice::from_ref(&node), |ptr, weaks| visited.push((ptr, weaks.len())));
+/// assert_eq!(visited, [(Rc::as_ptr(&node), 1)]);
+/// ```
+pub fn trace<T, F>(roots: &[Rc<T>], mut visit: F)
+    where
+        T: Trace,
+        F: FnMut(*const T, &[Weak<T>]),
+{
+    let mut seen = HashSet::new();
+    let mut stack: Vec<Rc<T>> = roots.to_vec();
+
+    while let Some(node) = stack.pop() {
+        if !seen.insert(Rc::as_ptr(&node)) {
+            continue;
+        }
+
+        let mut weaks = Vec::new();
+        node.trace(&mut |edge| match edge {
+            Edge::Strong(rc) => stack.push(rc.clone()),
+            Edge::Weak(weak) => weaks.push(weak.clone()),
+        });
+
+        visit(Rc::as_ptr(&node), &weaks);
+        stack.extend(weaks.iter().filter_map(Weak::upgrade));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    struct Node {
+        next: Option<Rc<Node>>,
+        back: RefCell<Weak<Node>>,
+    }
+
+    impl Trace for Node {
+        fn trace(&self, edges: &mut dyn FnMut(Edge<'_, Self>)) {
+            if let Some(next) = &self.next {
+                edges(Edge::Strong(next));
+            }
+            edges(Edge::Weak(&self.back.borrow()));
+        }
+    }
+
+    #[test]
+    fn test_cycle() {
+        // a -> b - - > a
+        let b = Rc::new(Node { next: None, back: RefCell::new(Weak::new()) });
+        let a = Rc::new(Node { next: Some(b.clone()), back: RefCell::new(Weak::new()) });
+        *b.back.borrow_mut() = Rc::downgrade(&a);
+
+        let mut visited = HashMap::new();
+        trace(std::slice::from_ref(&a), |ptr, weaks| {
+            let targets: Vec<_> = weaks.iter().map(Weak::upgrade).map(|rc| rc.map(|rc| Rc::as_ptr(&rc))).collect();
+            assert!(visited.insert(ptr, targets).is_none(), "each node must be visited once");
+        });
+
+        assert_eq!(visited.len(), 2, "all nodes must be visited");
+        assert_eq!(visited[&Rc::as_ptr(&a)], [None], "empty weak edge must be reported");
+        assert_eq!(visited[&Rc::as_ptr(&b)], [Some(Rc::as_ptr(&a))], "weak edge must be reported");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_pending() {
+        use crate::MaybeRc;
+
+        let maybe = MaybeRc::new();
+        let node = Rc::new(Node { next: None, back: RefCell::new(maybe.downgrade()) });
+
+        let mut visited = Vec::new();
+        trace(std::slice::from_ref(&node), |ptr, weaks| visited.push((ptr, weaks.len())));
+        assert_eq!(visited, [(Rc::as_ptr(&node), 1)], "pending node must be reported as an edge only");
+
+        let pending = maybe.materialize(Node { next: None, back: RefCell::new(Weak::new()) });
+
+        let mut visited = Vec::new();
+        trace(std::slice::from_ref(&node), |ptr, _| visited.push(ptr));
+        assert!(visited.contains(&Rc::as_ptr(&pending)), "materialized node must be traced");
+    }
+}
@@ -0,0 +1,196 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::MaybeRc;
+
+static NEXT_BUILDER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Helper for recursive descent parsers building trees with parent back-references
+///
+/// Every node is begun before recursing into its children, so they can capture the parent's
+/// `Weak<T>`, and finished once its children are built. Children are finished first,
+/// so the tree is materialized bottom-up.
+///
+/// Finishing a node begun by another builder panics. In debug builds the builder also
+/// panics on drop if some begun nodes were never finished (including dropped ones).
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::TreeBuilder;
+///
+/// struct Node {
+///     parent: Weak<Node>,
+///     children: Vec<Rc<Node>>,
+/// }
+///
+/// fn parse(builder: &TreeBuilder<Node>, depth: usize, parent: Weak<Node>) -> Rc<Node> {
+///     let (node, weak) = builder.begin_node();
+///     let children = match depth {
+///         0 => Vec::new(),
+///         _ => vec![parse(builder, depth - 1, weak)],
+///     };
+///     builder.finish(node, Node { parent, children })
+/// }
+///
+/// let builder = TreeBuilder::new();
+/// let root = parse(&builder, 2, Weak::new());
+/// let child = &root.children[0];
+/// assert!(Rc::ptr_eq(&child.parent.upgrade().unwrap(), &root));
+/// ```
+pub struct TreeBuilder<T> {
+    id: usize,
+    open: Cell<usize>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// A node begun by `TreeBuilder::begin_node` which is not finished yet
+pub struct BegunNode<T> {
+    maybe: MaybeRc<T>,
+    builder: usize,
+}
+
+impl<T> TreeBuilder<T> {
+    /// Constructs a new `TreeBuilder<T>`.
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_BUILDER_ID.fetch_add(1, Ordering::Relaxed),
+            open: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Begins a new node returning it together with a `Weak<T>` for its children.
+    pub fn begin_node(&self) -> (BegunNode<T>, Weak<T>) {
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+
+        self.open.set(self.open.get() + 1);
+        (BegunNode { maybe, builder: self.id }, weak)
+    }
+
+    /// Finishes a node begun by `begin_node` materializing it with `value`.
+    pub fn finish(&self, node: BegunNode<T>, value: T) -> Rc<T> {
+        assert_eq!(node.builder, self.id, "node wasn't begun by this builder");
+
+        let open = self.open.get().checked_sub(1).expect("more nodes finished than begun");
+        self.open.set(open);
+        node.maybe.materialize(value)
+    }
+
+    /// Returns the number of begun but not yet finished nodes.
+    pub fn open_nodes(&self) -> usize {
+        self.open.get()
+    }
+}
+
+impl<T> Default for TreeBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for TreeBuilder<T> {
+    fn drop(&mut self) {
+        // nodes are left unfinished on purpose when a parser bails out with a panic
+        if !thread::panicking() {
+            debug_assert_eq!(self.open.get(), 0, "all begun nodes must be finished");
+        }
+    }
+}
+
+impl<T> BegunNode<T> {
+    /// Creates a new `Weak<T>` pointer to this allocation.
+    pub fn downgrade(&self) -> Weak<T> {
+        self.maybe.downgrade()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    /// `name` or `(name child...)`
+    struct Ast {
+        name: char,
+        parent: Weak<Ast>,
+        children: Vec<Rc<Ast>>,
+    }
+
+    fn parse(builder: &TreeBuilder<Ast>, input: &mut Peekable<Chars<'_>>, parent: Weak<Ast>) -> Rc<Ast> {
+        let (node, weak) = builder.begin_node();
+        let mut children = Vec::new();
+
+        let name = match input.next() {
+            Some('(') => {
+                let name = input.next().expect("list must have a name");
+                while input.next_if_eq(&' ').is_some() {
+                    children.push(parse(builder, input, weak.clone()));
+                }
+                assert_eq!(input.next(), Some(')'), "list must be closed");
+                name
+            }
+            Some(name) => name,
+            None => panic!("unexpected end of input"),
+        };
+
+        builder.finish(node, Ast { name, parent, children })
+    }
+
+    fn check_parents(node: &Rc<Ast>) {
+        for child in &node.children {
+            let parent = child.parent.upgrade().expect("parent must be upgradable");
+            assert!(Rc::ptr_eq(&parent, node), "child {} must point to its parent {}", child.name, node.name);
+            check_parents(child);
+        }
+    }
+
+    #[test]
+    fn test_parse_ast() {
+        let builder = TreeBuilder::new();
+        let root = parse(&builder, &mut "(a (b c d) e)".chars().peekable(), Weak::new());
+        assert_eq!(builder.open_nodes(), 0, "all nodes must be finished");
+
+        let names: Vec<_> = root.children.iter().map(|child| child.name).collect();
+        assert_eq!((root.name, names), ('a', vec!['b', 'e']), "tree doesn't match the input");
+        assert_eq!(root.children[0].children.len(), 2, "tree doesn't match the input");
+        assert!(root.parent.upgrade().is_none(), "root must not have a parent");
+        check_parents(&root);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "all begun nodes must be finished")]
+    fn test_unfinished_node() {
+        let builder = TreeBuilder::<usize>::new();
+        let _node = builder.begin_node();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "all begun nodes must be finished")]
+    fn test_dropped_node() {
+        let builder = TreeBuilder::<usize>::new();
+        drop(builder.begin_node());
+
+        let (node, _) = builder.begin_node();
+        builder.finish(node, 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "node wasn't begun by this builder")]
+    fn test_foreign_node() {
+        let first = TreeBuilder::new();
+        let second = TreeBuilder::new();
+
+        let (node, _) = first.begin_node();
+        second.finish(node, 42);
+    }
+}
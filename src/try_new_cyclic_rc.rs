@@ -35,7 +35,34 @@ pub fn try_new_cyclic_rc<F, T, E>(f: F) -> Result<Rc<T>, E>
     where
         F: FnOnce(&Weak<T>) -> Result<T, E>,
 {
-    let mut error = None;
+    try_new_cyclic_rc_with(|weak| f(weak).map(|value| (value, ()))).map(|(rc, ())| rc)
+}
+
+/// Helper function for creating cyclic `Rc` which might fail and also produces an extra value
+///
+/// Same as `try_new_cyclic_rc` but allows closure to return a side value alongside
+/// the object itself, which is returned together with the newly created `Rc`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::rc::{Rc, Weak};
+/// use maybe_rc::try_new_cyclic_rc_with;
+///
+/// struct Node(Weak<Node>);
+///
+/// let (rc, name) = try_new_cyclic_rc_with(|weak| {
+///     Ok::<_, ()>((Node(weak.clone()), "node"))
+/// }).unwrap();
+///
+/// assert_eq!(name, "node");
+/// assert_eq!(rc.0.as_ptr(), Rc::as_ptr(&rc));
+/// ```
+pub fn try_new_cyclic_rc_with<F, T, R, E>(f: F) -> Result<(Rc<T>, R), E>
+    where
+        F: FnOnce(&Weak<T>) -> Result<(T, R), E>,
+{
+    let mut result = None;
 
     let strong = Rc::<MaybeUninit<T>>::new_cyclic(|weak| {
         // SAFETY: T cannot be accessed from here, safe to strip down `MaybeUninit`
@@ -44,21 +71,26 @@ pub fn try_new_cyclic_rc<F, T, E>(f: F) -> Result<Rc<T>, E>
         };
         match f(&weak) {
             Err(e) => {
-                error = Some(e);
+                result = Some(Err(e));
                 MaybeUninit::uninit()
             }
-            Ok(e) => MaybeUninit::new(e),
+            Ok((value, extra)) => {
+                result = Some(Ok(extra));
+                MaybeUninit::new(value)
+            }
         }
     });
 
-    if let Some(error) = error {
-        return Err(error);
-    }
+    let extra = match result {
+        Some(Ok(extra)) => extra,
+        Some(Err(error)) => return Err(error),
+        None => unreachable!("closure is always called by `Rc::new_cyclic`"),
+    };
 
     // SAFETY: T is guaranteed to be initialized by now, safe to strip down `MaybeUninit`
-    Ok(unsafe {
+    Ok((unsafe {
         Rc::from_raw(Rc::into_raw(strong).cast())
-    })
+    }, extra))
 }
 
 #[cfg(test)]
@@ -89,4 +121,43 @@ mod tests {
         assert!(rc.is_err(), "must fail");
         assert_eq!(rc, Err(42), "incorrect error value");
     }
+
+    #[test]
+    fn test_with_ok() {
+        struct Wrapper(usize, Weak<Wrapper>);
+
+        let result = try_new_cyclic_rc_with(|weak| {
+            Ok::<_, ()>((Wrapper(42, weak.clone()), "extra"))
+        });
+
+        assert!(result.is_ok(), "must not fail");
+
+        let (rc, extra) = result.unwrap();
+        assert_eq!(rc.0, 42, "incorrect ok value");
+        assert_eq!(extra, "extra", "incorrect extra value");
+        assert_eq!(rc.1.as_ptr(), Rc::as_ptr(&rc), "Weak and Rc point to a different objects");
+    }
+
+    #[test]
+    fn test_with_err() {
+        struct InnerT;
+
+        impl Drop for InnerT {
+            fn drop(&mut self) {
+                panic!("must not be dropped");
+            }
+        }
+
+        let mut leaked = None;
+        let result = try_new_cyclic_rc_with(|weak: &Weak<InnerT>| {
+            leaked = Some(weak.clone());
+            Err::<(InnerT, ()), usize>(42)
+        });
+
+        assert!(result.is_err(), "must fail");
+        assert_eq!(result.err(), Some(42), "incorrect error value");
+
+        let leaked = leaked.unwrap();
+        assert!(leaked.upgrade().is_none(), "must not be upgradable");
+    }
 }
@@ -0,0 +1,156 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::{Rc, Weak};
+
+struct Entry<T> {
+    weak: Weak<T>,
+    materialized: bool,
+}
+
+/// A directory of `Weak<T>` references to graph nodes
+///
+/// Nodes can be registered before they are materialized (e.g. right after `MaybeRc::new`)
+/// and looked up later, entries of nodes that were materialized and dropped since are pruned.
+///
+/// `Weak<T>` can't tell a node that is not materialized yet from an already dropped one, so entries
+/// are only pruned after they were seen alive at least once. Entries of nodes that will never be
+/// materialized have to be removed manually.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+/// use maybe_rc::WeakRegistry;
+///
+/// let mut registry = WeakRegistry::new();
+///
+/// let node = Rc::new(42);
+/// registry.register("node", Rc::downgrade(&node));
+/// assert_eq!(registry.get("node").map(|e| *e), Some(42));
+///
+/// drop(node);
+/// assert!(registry.get("node").is_none());
+/// assert!(registry.is_empty());
+/// ```
+pub struct WeakRegistry<K, T> {
+    entries: HashMap<K, Entry<T>>,
+}
+
+impl<K: Hash + Eq, T> WeakRegistry<K, T> {
+    /// Constructs a new empty `WeakRegistry<K, T>`.
+    pub fn new() -> Self {
+        Self { entries: HashMap::new() }
+    }
+
+    /// Registers `weak` under `key` returning the previously registered reference.
+    pub fn register(&mut self, key: K, weak: Weak<T>) -> Option<Weak<T>> {
+        let materialized = weak.strong_count() > 0;
+        self.entries.insert(key, Entry { weak, materialized })
+            .map(|entry| entry.weak)
+    }
+
+    /// Upgrades the reference registered under `key`.
+    ///
+    /// Returns `None` if node is not materialized yet or was already dropped,
+    /// in the latter case the entry is removed.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<Rc<T>>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+    {
+        let entry = self.entries.get_mut(key)?;
+
+        match entry.weak.upgrade() {
+            Some(rc) => {
+                entry.materialized = true;
+                Some(rc)
+            }
+            None => {
+                if entry.materialized {
+                    self.entries.remove(key);
+                }
+                None
+            }
+        }
+    }
+
+    /// Removes the reference registered under `key`.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Weak<T>>
+        where
+            K: Borrow<Q>,
+            Q: Hash + Eq + ?Sized,
+    {
+        self.entries.remove(key).map(|entry| entry.weak)
+    }
+
+    /// Removes all entries of nodes that were materialized and dropped since.
+    pub fn prune(&mut self) {
+        self.entries.retain(|_, entry| {
+            let alive = entry.weak.strong_count() > 0;
+            entry.materialized |= alive;
+            alive || !entry.materialized
+        });
+    }
+
+    /// Returns the number of registered entries, including not yet pruned ones.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no registered entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K: Hash + Eq, T> Default for WeakRegistry<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::try_new_cyclic_rc;
+
+    #[test]
+    fn test_lifecycle() {
+        let mut registry = WeakRegistry::new();
+
+        let rc = try_new_cyclic_rc(|weak: &Weak<usize>| {
+            registry.register(1, weak.clone());
+            assert!(registry.get(&1).is_none(), "must not be materialized");
+            assert_eq!(registry.len(), 1, "must not be pruned before materialization");
+            Ok::<_, ()>(42)
+        }).unwrap();
+
+        let node = registry.get(&1).expect("must be materialized");
+        assert!(Rc::ptr_eq(&node, &rc), "must be the registered node");
+
+        drop(node);
+        drop(rc);
+        assert!(registry.get(&1).is_none(), "must be dropped");
+        assert!(registry.is_empty(), "must be pruned");
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut registry = WeakRegistry::new();
+
+        let alive = Rc::new(1);
+        let dropped = Rc::new(2);
+        registry.register("alive", Rc::downgrade(&alive));
+        registry.register("dropped", Rc::downgrade(&dropped));
+        registry.register("pending", Weak::new());
+
+        drop(dropped);
+        registry.prune();
+
+        assert_eq!(registry.len(), 2, "only dropped node must be pruned");
+        assert_eq!(registry.get("alive").map(|e| *e), Some(1), "alive node must stay");
+        assert!(registry.remove("pending").is_some(), "pending node must stay");
+    }
+}
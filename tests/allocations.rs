@@ -0,0 +1,44 @@
+#![cfg(feature = "nightly")]
+
+use std::sync::Arc;
+
+use maybe_rc::{MaybeArc, MaybeRc};
+
+use common::{live_allocations, CountingAllocator};
+
+mod common;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_arc_materialize_without_weak() {
+    let live = live_allocations();
+
+    let arc = MaybeArc::new().materialize(42usize);
+    assert_eq!(live_allocations() - live, 1, "must use a single allocation");
+
+    let expected = Arc::new(42usize);
+    assert_eq!(*arc, *expected, "value is not what was provided");
+    assert_eq!(Arc::strong_count(&arc), Arc::strong_count(&expected), "strong count differs from Arc::new");
+    assert_eq!(Arc::weak_count(&arc), Arc::weak_count(&expected), "weak count differs from Arc::new");
+
+    drop(expected);
+    drop(arc);
+    assert_eq!(live_allocations(), live, "allocation must not leak");
+}
+
+#[test]
+fn test_rc_try_materialize_or_drop_err() {
+    let live = live_allocations();
+
+    let result = MaybeRc::<usize>::new().try_materialize_or_drop(|weak| {
+        assert!(live_allocations() > live, "slot must be allocated while building");
+        assert!(weak.upgrade().is_none(), "must not be upgradable while building");
+        Err("failed")
+    });
+
+    assert_eq!(result.expect_err("must fail"), "failed", "error is not what was returned");
+
+    assert_eq!(live_allocations(), live, "slot must be freed on error");
+}
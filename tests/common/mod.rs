@@ -0,0 +1,28 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Global allocator that counts live allocations per thread
+///
+/// Tests run in parallel so only allocations made by the current thread are visible.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = LIVE.try_with(|live| live.set(live.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = LIVE.try_with(|live| live.set(live.get() - 1));
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Number of allocations made and not yet freed by the current thread
+pub fn live_allocations() -> isize {
+    LIVE.with(|live| live.get())
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "nightly")]
+
+use std::rc::{self, Rc};
+use std::sync::{self, Arc};
+
+use maybe_rc::{MaybeArc, MaybeRc};
+
+fn rc_counts<T>(rc: &Rc<T>) -> (usize, usize) {
+    (Rc::strong_count(rc), Rc::weak_count(rc))
+}
+
+fn arc_counts<T>(arc: &Arc<T>) -> (usize, usize) {
+    (Arc::strong_count(arc), Arc::weak_count(arc))
+}
+
+fn check_rc(weaks: usize) {
+    let mut std_weaks: Vec<rc::Weak<usize>> = Vec::new();
+    let std_rc = Rc::new_cyclic(|weak| {
+        std_weaks.extend((0..weaks).map(|_| weak.clone()));
+        42
+    });
+
+    let maybe = MaybeRc::new();
+    let mut maybe_weaks: Vec<_> = (0..weaks).map(|_| maybe.downgrade()).collect();
+    let maybe_rc = maybe.materialize(42);
+
+    assert_eq!(rc_counts(&maybe_rc), rc_counts(&std_rc), "counts differ after materialization with {} weaks", weaks);
+
+    let std_clone = std_rc.clone();
+    let maybe_clone = maybe_rc.clone();
+    assert_eq!(rc_counts(&maybe_rc), rc_counts(&std_rc), "counts differ after clone with {} weaks", weaks);
+
+    let std_extra = Rc::downgrade(&std_rc);
+    let maybe_extra = Rc::downgrade(&maybe_rc);
+    assert_eq!(rc_counts(&maybe_rc), rc_counts(&std_rc), "counts differ after downgrade with {} weaks", weaks);
+
+    std_weaks.clear();
+    maybe_weaks.clear();
+    assert_eq!(rc_counts(&maybe_rc), rc_counts(&std_rc), "counts differ after dropping {} weaks", weaks);
+
+    drop((std_clone, maybe_clone));
+    assert_eq!(rc_counts(&maybe_rc), rc_counts(&std_rc), "counts differ after dropping clone with {} weaks", weaks);
+
+    drop((std_rc, maybe_rc));
+    assert_eq!(std_extra.strong_count(), maybe_extra.strong_count(), "strong counts differ after drop");
+    assert_eq!(maybe_extra.weak_count(), std_extra.weak_count(), "weak counts differ after drop");
+}
+
+fn check_arc(weaks: usize) {
+    let mut std_weaks: Vec<sync::Weak<usize>> = Vec::new();
+    let std_arc = Arc::new_cyclic(|weak| {
+        std_weaks.extend((0..weaks).map(|_| weak.clone()));
+        42
+    });
+
+    let maybe = MaybeArc::new();
+    let mut maybe_weaks: Vec<_> = (0..weaks).map(|_| maybe.downgrade()).collect();
+    let maybe_arc = maybe.materialize(42);
+
+    assert_eq!(arc_counts(&maybe_arc), arc_counts(&std_arc), "counts differ after materialization with {} weaks", weaks);
+
+    let std_clone = std_arc.clone();
+    let maybe_clone = maybe_arc.clone();
+    assert_eq!(arc_counts(&maybe_arc), arc_counts(&std_arc), "counts differ after clone with {} weaks", weaks);
+
+    let std_extra = Arc::downgrade(&std_arc);
+    let maybe_extra = Arc::downgrade(&maybe_arc);
+    assert_eq!(arc_counts(&maybe_arc), arc_counts(&std_arc), "counts differ after downgrade with {} weaks", weaks);
+
+    std_weaks.clear();
+    maybe_weaks.clear();
+    assert_eq!(arc_counts(&maybe_arc), arc_counts(&std_arc), "counts differ after dropping {} weaks", weaks);
+
+    drop((std_clone, maybe_clone));
+    assert_eq!(arc_counts(&maybe_arc), arc_counts(&std_arc), "counts differ after dropping clone with {} weaks", weaks);
+
+    drop((std_arc, maybe_arc));
+    assert_eq!(std_extra.strong_count(), maybe_extra.strong_count(), "strong counts differ after drop");
+    assert_eq!(maybe_extra.weak_count(), std_extra.weak_count(), "weak counts differ after drop");
+}
+
+#[test]
+fn test_rc_counts_match_new_cyclic() {
+    for weaks in [0, 1, 16] {
+        check_rc(weaks);
+    }
+}
+
+#[test]
+fn test_arc_counts_match_new_cyclic() {
+    for weaks in [0, 1, 16] {
+        check_arc(weaks);
+    }
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "nightly")]
+#![feature(allocator_api)]
+
+use std::alloc::{AllocError, Allocator, GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ptr::NonNull;
+use std::rc::{Rc, Weak};
+use std::sync::{self, Arc};
+
+use maybe_rc::{build_topological, Deps, MaybeArc};
+
+const ARENA_SIZE: usize = 64 * 1024;
+
+/// Bump allocator handing out memory in allocation order
+///
+/// Unlike the global allocator, offsets of allocations from the arena start only depend
+/// on the sequence of allocations, so two identical graph constructions in fresh arenas
+/// produce identical layouts.
+struct DeterministicAllocator {
+    base: NonNull<u8>,
+    next: Cell<usize>,
+}
+
+impl DeterministicAllocator {
+    fn new() -> Self {
+        // SAFETY: layout has non-zero size
+        let base = unsafe { System.alloc(Self::layout()) };
+        Self { base: NonNull::new(base).expect("arena allocation failed"), next: Cell::new(0) }
+    }
+
+    fn layout() -> Layout {
+        Layout::from_size_align(ARENA_SIZE, 4096).unwrap()
+    }
+
+    fn offset<T: ?Sized>(&self, ptr: *const T) -> usize {
+        ptr as *const u8 as usize - self.base.as_ptr() as usize
+    }
+}
+
+unsafe impl Allocator for DeterministicAllocator {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let start = self.next.get().next_multiple_of(layout.align());
+        let end = start.checked_add(layout.size()).filter(|&end| end <= ARENA_SIZE).ok_or(AllocError)?;
+        self.next.set(end);
+
+        // SAFETY: `start..end` is within the arena
+        let ptr = unsafe { NonNull::new_unchecked(self.base.as_ptr().add(start)) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    // memory is released all at once with the arena
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+
+impl Drop for DeterministicAllocator {
+    fn drop(&mut self) {
+        // SAFETY: allocated in `new` with the same layout
+        unsafe { System.dealloc(self.base.as_ptr(), Self::layout()) }
+    }
+}
+
+struct ArenaNode<'a> {
+    id: usize,
+    children: Vec<Arc<ArenaNode<'a>, &'a DeterministicAllocator>>,
+    back: Vec<sync::Weak<ArenaNode<'a>, &'a DeterministicAllocator>>,
+}
+
+type ArenaWeak<'a> = sync::Weak<ArenaNode<'a>, &'a DeterministicAllocator>;
+
+/// Builds a binary tree of `depth` where every node has a back-edge to the root
+fn build_arena<'a>(
+    alloc: &'a DeterministicAllocator,
+    depth: usize,
+    next_id: &mut usize,
+    root: Option<&ArenaWeak<'a>>,
+) -> Arc<ArenaNode<'a>, &'a DeterministicAllocator> {
+    let maybe = MaybeArc::new_in(alloc);
+    let weak = maybe.downgrade();
+    let root = root.unwrap_or(&weak);
+
+    let id = *next_id;
+    *next_id += 1;
+
+    let children = match depth {
+        0 => Vec::new(),
+        _ => (0..2).map(|_| build_arena(alloc, depth - 1, next_id, Some(root))).collect(),
+    };
+
+    maybe.materialize(ArenaNode { id, children, back: vec![root.clone()] })
+}
+
+/// `(id, arena offset, children ids, back-edge ids)` of every node in depth-first order
+fn arena_snapshot(alloc: &DeterministicAllocator, node: &ArenaNode<'_>, out: &mut Vec<(usize, usize, Vec<usize>, Vec<usize>)>) {
+    out.push((
+        node.id,
+        alloc.offset(node),
+        node.children.iter().map(|child| child.id).collect(),
+        node.back.iter().map(|weak| weak.upgrade().expect("back-edge must be alive").id).collect(),
+    ));
+
+    for child in &node.children {
+        arena_snapshot(alloc, child, out);
+    }
+}
+
+fn arena_run() -> Vec<(usize, usize, Vec<usize>, Vec<usize>)> {
+    let alloc = DeterministicAllocator::new();
+    let root = build_arena(&alloc, 4, &mut 0, None);
+
+    let mut snapshot = Vec::new();
+    arena_snapshot(&alloc, &root, &mut snapshot);
+    snapshot
+}
+
+#[test]
+fn test_arena_layout_reproducible() {
+    let first = arena_run();
+    let second = arena_run();
+
+    assert_eq!(first.len(), 31, "all nodes must be built");
+    assert_eq!(first, second, "identical constructions must produce identical layouts");
+}
+
+struct Node {
+    children: Vec<Rc<Node>>,
+    back: Vec<Weak<Node>>,
+}
+
+/// Children and back-edges of every node as indices into the built nodes
+fn topology(nodes: &[Rc<Node>]) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let index_of = |ptr: *const Node| {
+        nodes.iter().position(|node| Rc::as_ptr(node) == ptr).expect("edge must point to a built node")
+    };
+
+    nodes.iter()
+        .map(|node| (
+            node.children.iter().map(|child| index_of(Rc::as_ptr(child))).collect(),
+            node.back.iter().map(|weak| index_of(weak.as_ptr())).collect(),
+        ))
+        .collect()
+}
+
+fn topological_run(deps: &[Vec<usize>]) -> Vec<(Vec<usize>, Vec<usize>)> {
+    let nodes = build_topological(deps, |deps: Deps<'_, Node>| Node {
+        // every node points back to all nodes after it
+        back: (deps.index + 1..6).map(|index| deps.weak(index)).collect(),
+        children: deps.children,
+    }).expect("graph must be acyclic");
+
+    topology(&nodes)
+}
+
+#[test]
+fn test_topology_reproducible() {
+    let deps = vec![vec![1, 2], vec![3], vec![3, 4], vec![5], vec![5], vec![]];
+
+    // allocations kept between runs shift where the next graph lands
+    let mut runs = Vec::new();
+    let mut shift = Vec::new();
+    for _ in 0..4 {
+        runs.push(topological_run(&deps));
+        shift.push(Box::new([0u8; 256]));
+    }
+
+    for run in &runs[1..] {
+        assert_eq!(run, &runs[0], "topology must not depend on allocation addresses");
+    }
+    assert_eq!(runs[0][0], (vec![1, 2], vec![1, 2, 3, 4, 5]), "topology must match the dependencies");
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "nightly")]
+
+use std::rc::{Rc, Weak};
+
+use maybe_rc::MaybeRc;
+
+use common::{live_allocations, CountingAllocator};
+
+mod common;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Node with strong edges to its children and weak edges back to some of its ancestors
+struct Node {
+    id: usize,
+    children: Vec<Rc<Node>>,
+    back: Vec<Weak<Node>>,
+}
+
+/// Graph description: children of every node and `(from, to)` back-edges
+struct Spec {
+    children: Vec<Vec<usize>>,
+    back: Vec<(usize, usize)>,
+}
+
+/// Builds the node `id` while all of its ancestors are still being constructed
+fn build(spec: &Spec, id: usize, ancestors: &mut Vec<(usize, Weak<Node>)>) -> Rc<Node> {
+    let maybe = MaybeRc::new();
+    ancestors.push((id, maybe.downgrade()));
+
+    let children = spec.children[id].iter()
+        .map(|&child| build(spec, child, ancestors))
+        .collect();
+
+    ancestors.pop();
+
+    let back = spec.back.iter()
+        .filter(|&&(from, _)| from == id)
+        .map(|&(_, to)| {
+            let (_, weak) = ancestors.iter()
+                .find(|(ancestor, _)| *ancestor == to)
+                .expect("back-edge must point to an ancestor");
+            assert!(weak.upgrade().is_none(), "ancestor must not be materialized yet");
+            weak.clone()
+        })
+        .collect();
+
+    maybe.materialize(Node { id, children, back })
+}
+
+/// Collects all back-edges that point to a node on the current path
+fn find_back_edges(node: &Rc<Node>, path: &mut Vec<*const Node>, found: &mut Vec<(usize, usize)>) {
+    path.push(Rc::as_ptr(node));
+
+    for weak in &node.back {
+        let target = weak.upgrade().expect("ancestor must be alive");
+        if path.contains(&Rc::as_ptr(&target)) {
+            found.push((node.id, target.id));
+        }
+    }
+
+    for child in &node.children {
+        find_back_edges(child, path, found);
+    }
+
+    path.pop();
+}
+
+#[test]
+fn test_back_edges() {
+    let live = live_allocations();
+
+    //     0
+    //    / \
+    //   1   2 - - > 0
+    //   |
+    //   3 - - > 0, 1
+    let spec = Spec {
+        children: vec![vec![1, 2], vec![3], vec![], vec![]],
+        back: vec![(2, 0), (3, 0), (3, 1)],
+    };
+
+    let root = build(&spec, 0, &mut Vec::new());
+
+    let mut found = Vec::new();
+    find_back_edges(&root, &mut Vec::new(), &mut found);
+    found.sort_unstable();
+    assert_eq!(found, spec.back, "all back-edges must be found");
+
+    let weak = Rc::downgrade(&root.children[0].children[0]);
+    drop(root);
+    assert!(weak.upgrade().is_none(), "graph must be dropped with its root");
+
+    drop(weak);
+    drop(found);
+    drop(spec);
+
+    assert_eq!(live_allocations(), live, "graph must not leak");
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "nightly")]
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+use maybe_rc::{try_new_cyclic_rc, MaybeArc, MaybeRc};
+
+use common::{live_allocations, CountingAllocator};
+
+mod common;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+    static DROPS: Cell<usize> = const { Cell::new(0) };
+    static EXPECTING_PANIC: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Silences panics expected by `assert_panic_safe`, all other panics go to the previous hook
+///
+/// Default hook allocates while reporting, which would be counted as a leak.
+fn silence_expected_panics() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !EXPECTING_PANIC.with(|expecting| expecting.get()) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Counts its drops and optionally panics while being dropped
+struct Tracked {
+    panic_on_drop: bool,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        DROPS.with(|drops| drops.set(drops.get() + 1));
+        if self.panic_on_drop {
+            panic!("drop panicked");
+        }
+    }
+}
+
+fn drops() -> usize {
+    DROPS.with(|drops| drops.get())
+}
+
+/// Runs `f` expecting a panic and checks that nothing was leaked and `Tracked` was dropped `expected_drops` times
+fn assert_panic_safe<F: FnOnce()>(expected_drops: usize, f: F) {
+    silence_expected_panics();
+
+    let live = live_allocations();
+    let drops_before = drops();
+
+    EXPECTING_PANIC.with(|expecting| expecting.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    EXPECTING_PANIC.with(|expecting| expecting.set(false));
+    assert!(result.is_err(), "must panic");
+    drop(result);
+
+    assert_eq!(drops() - drops_before, expected_drops, "value must be dropped exactly {} times", expected_drops);
+
+    assert_eq!(live_allocations(), live, "allocation must not leak");
+}
+
+#[test]
+fn test_rc_drop_panics() {
+    assert_panic_safe(1, || {
+        let rc = MaybeRc::new().materialize(Tracked { panic_on_drop: true });
+        drop(rc);
+    });
+}
+
+#[test]
+fn test_rc_unique_drop_panics() {
+    assert_panic_safe(1, || {
+        let unique = MaybeRc::new().materialize_unique(Tracked { panic_on_drop: true });
+        drop(unique);
+    });
+}
+
+#[test]
+fn test_rc_drop_panics_with_weak() {
+    assert_panic_safe(1, || {
+        let maybe = MaybeRc::new();
+        let weak = maybe.downgrade();
+        let rc = maybe.materialize(Tracked { panic_on_drop: true });
+
+        // weak is dropped during unwinding and must free the allocation
+        let _weak = weak;
+        drop(rc);
+    });
+}
+
+#[test]
+fn test_rc_materialize_then_panics() {
+    assert_panic_safe(1, || {
+        let maybe = MaybeRc::new();
+        let _weak = maybe.downgrade();
+        maybe.materialize_then(Tracked { panic_on_drop: false }, |_| panic!("callback panicked"));
+    });
+}
+
+#[test]
+fn test_rc_uninit_never_dropped() {
+    assert_panic_safe(0, || {
+        let maybe = MaybeRc::<Tracked>::new();
+        let _weak = maybe.downgrade();
+        drop(maybe);
+        panic!("abandoned");
+    });
+}
+
+#[test]
+fn test_arc_drop_panics() {
+    assert_panic_safe(1, || {
+        let arc = MaybeArc::new().materialize(Tracked { panic_on_drop: true });
+        drop(arc);
+    });
+}
+
+#[test]
+fn test_arc_drop_panics_with_weak() {
+    assert_panic_safe(1, || {
+        let maybe = MaybeArc::new();
+        let weak = maybe.downgrade();
+        let arc = maybe.materialize(Tracked { panic_on_drop: true });
+
+        // weak is dropped during unwinding and must free the allocation
+        let _weak = weak;
+        drop(arc);
+    });
+}
+
+#[test]
+fn test_arc_materialize_then_panics() {
+    assert_panic_safe(1, || {
+        let maybe = MaybeArc::new();
+        let _weak = maybe.downgrade();
+        maybe.materialize_then(Tracked { panic_on_drop: false }, |_| panic!("callback panicked"));
+    });
+}
+
+#[test]
+fn test_arc_uninit_never_dropped() {
+    assert_panic_safe(0, || {
+        let maybe = MaybeArc::<Tracked>::new();
+        let _weak = maybe.downgrade();
+        drop(maybe);
+        panic!("abandoned");
+    });
+}
+
+#[test]
+fn test_try_new_cyclic_rc_panics() {
+    assert_panic_safe(1, || {
+        let _ = try_new_cyclic_rc(|weak| -> Result<Tracked, ()> {
+            let _tracked = Tracked { panic_on_drop: false };
+            let _weak = weak.clone();
+            panic!("constructor panicked")
+        });
+    });
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "static-buffer")]
+#![feature(allocator_api)]
+
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::rc::{self, Rc};
+use std::sync::{Arc, Weak};
+
+use maybe_rc::{MaybeArc, MaybeRc, StaticBufferAllocator};
+
+use common::{live_allocations, CountingAllocator};
+
+mod common;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static mut BUFFER: [MaybeUninit<u8>; 128] = [MaybeUninit::uninit(); 128];
+static mut RC_BUFFER: [MaybeUninit<u8>; 128] = [MaybeUninit::uninit(); 128];
+
+struct Node {
+    value: usize,
+    me: Weak<Node, StaticBufferAllocator>,
+}
+
+struct RcNode {
+    value: usize,
+    me: rc::Weak<RcNode, StaticBufferAllocator>,
+}
+
+#[test]
+fn test_static_buffer_without_heap() {
+    // SAFETY: the buffer is only borrowed by this test
+    let buf = unsafe { &mut *ptr::addr_of_mut!(BUFFER) };
+    let range = buf.as_ptr_range();
+
+    let live = live_allocations();
+
+    let maybe = MaybeArc::from_uninit_buffer(buf);
+    let weak = maybe.downgrade();
+    assert!(weak.upgrade().is_none(), "must not be upgradable before materialization");
+
+    let arc = maybe.materialize(Node { value: 42, me: weak.clone() });
+    let upgraded = weak.upgrade().expect("must be upgradable after materialization");
+    assert!(Arc::ptr_eq(&upgraded, &arc), "must upgrade to the materialized node");
+    assert!(Weak::ptr_eq(&arc.me, &weak), "self weak must point to the node");
+    assert_eq!(upgraded.value, 42, "value is not what was provided");
+    assert!(range.contains(&Arc::as_ptr(&arc).cast()), "node must be placed into the buffer");
+
+    drop((arc, upgraded));
+    assert!(weak.upgrade().is_none(), "must not be upgradable after drop");
+    drop(weak);
+
+    assert_eq!(live_allocations(), live, "heap must not be used");
+}
+
+#[test]
+#[should_panic(expected = "too small")]
+fn test_static_buffer_too_small() {
+    let buf = Box::leak(Box::new([MaybeUninit::uninit(); 16]));
+    let _ = MaybeArc::<[u64; 4], _>::from_uninit_buffer(buf);
+}
+
+#[test]
+fn test_rc_static_buffer_without_heap() {
+    // SAFETY: the buffer is only borrowed by this test
+    let buf = unsafe { &mut *ptr::addr_of_mut!(RC_BUFFER) };
+    let range = buf.as_ptr_range();
+
+    let live = live_allocations();
+
+    let maybe = MaybeRc::from_uninit_buffer(buf);
+    let weak = maybe.downgrade();
+    assert!(weak.upgrade().is_none(), "must not be upgradable before materialization");
+
+    let rc = maybe.materialize(RcNode { value: 42, me: weak.clone() });
+    let upgraded = weak.upgrade().expect("must be upgradable after materialization");
+    assert!(Rc::ptr_eq(&upgraded, &rc), "must upgrade to the materialized node");
+    assert!(rc::Weak::ptr_eq(&rc.me, &weak), "self weak must point to the node");
+    assert_eq!(upgraded.value, 42, "value is not what was provided");
+    assert!(range.contains(&Rc::as_ptr(&rc).cast()), "node must be placed into the buffer");
+
+    drop((rc, upgraded));
+    assert!(weak.upgrade().is_none(), "must not be upgradable after drop");
+    drop(weak);
+
+    assert_eq!(live_allocations(), live, "heap must not be used");
+}
+
+#[test]
+#[should_panic(expected = "too small")]
+fn test_rc_static_buffer_too_small() {
+    let buf = Box::leak(Box::new([MaybeUninit::uninit(); 16]));
+    let _ = MaybeRc::<[u64; 4], _>::from_uninit_buffer(buf);
+}